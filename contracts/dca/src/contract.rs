@@ -2,11 +2,16 @@ use crate::error::{ContractError, ContractResult};
 use crate::execute::*;
 use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 use crate::query::*;
-use crate::state::{Config, PairData, Schedules, CONFIG, SCHEDULES};
+use crate::reply::ReplyPayload;
+use crate::state::{
+    Config, ExecutionVenueConfig, PriceFeedConfig, ScheduleOrderType, ScheduleSide, Schedules,
+    ASSETS, BATCH_DUST, CONFIG, PENDING_BATCH, SCHEDULES,
+};
 use crate::utils::*;
-use cosmwasm_std::{attr, entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response};
+use cosmwasm_std::{
+    attr, entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, Uint128,
+};
 use cw2::set_contract_version;
-use neutron_std::types::slinky::types::v1::CurrencyPair;
 
 ///////////////
 /// MIGRATE ///
@@ -34,6 +39,26 @@ pub fn instantiate(
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     msg.validate()?;
     let owner = deps.api.addr_validate(&msg.owner)?;
+    if let Some(price_aggregation) = &msg.price_aggregation {
+        for feed in price_aggregation.feeds.iter() {
+            if let PriceFeedConfig::Contract { address } = feed {
+                deps.api.addr_validate(address)?;
+            }
+        }
+    }
+    if let Some(ExecutionVenueConfig::Astroport { pair_address }) = &msg.execution_venue {
+        deps.api.addr_validate(pair_address)?;
+    }
+    for asset in msg.assets.iter() {
+        validate_asset(&deps.as_ref(), asset)?;
+        ASSETS.save(deps.storage, &asset.denom, asset)?;
+    }
+    for venue in msg.venues.iter().flatten() {
+        for hop in venue.hops.iter() {
+            validate_registered_denom(&deps.as_ref(), hop)?;
+        }
+    }
+
     let denom_ntrn = msg.denom_ntrn.clone();
     let denom_usd = msg.denom_usd.clone();
     let id = get_pair_id_str(&denom_ntrn, &denom_usd);
@@ -43,23 +68,35 @@ pub fn instantiate(
         querier: deps.querier,
     };
 
-    let currency_pair = CurrencyPair {
-        base: "NTRN".to_string(),
-        quote: "USD".to_string(),
-    };
-
-    validate_market(&deps_readonly, &env, &currency_pair, msg.max_block_old)?;
-
-    let pairs = PairData {
-        denom_ntrn: denom_ntrn.clone(),
-        denom_usd: denom_usd.clone(),
-        currency_pair,
-        pair_id: id.clone(),
-    };
+    let pairs = build_pair_data(
+        &deps_readonly,
+        &env,
+        &denom_ntrn,
+        &denom_usd,
+        &msg.oracle_policy,
+    )?;
+    if msg.depeg_guard.is_some() {
+        let stable_pair = stable_currency_pair(deps_readonly.storage, &denom_usd)?;
+        validate_market(&deps_readonly, &env, &stable_pair, &msg.oracle_policy)?;
+    }
 
     let config = Config {
         pair_data: pairs.clone(),
-        max_blocks_old: msg.max_block_old,
+        oracle_policy: msg.oracle_policy.clone(),
+        dex_fallback: msg.dex_fallback.clone(),
+        max_dex_deviation_bps: msg.max_dex_deviation_bps,
+        price_aggregation: msg.price_aggregation.clone(),
+        depeg_guard: msg.depeg_guard.clone(),
+        venues: msg.venues.clone().unwrap_or_default(),
+        execution_venue: msg
+            .execution_venue
+            .clone()
+            .unwrap_or(ExecutionVenueConfig::NeutronDex {}),
+        liquidity_check: msg.liquidity_check.clone(),
+        run_cap: msg.run_cap.clone(),
+        batch_mode: msg.batch_mode.unwrap_or(false),
+        max_consecutive_failures: msg.max_consecutive_failures,
+        min_tradable_amounts: msg.min_tradable_amounts.clone().unwrap_or_default(),
         owner: owner.clone(),
         max_schedules: msg.max_schedules,
     };
@@ -70,12 +107,21 @@ pub fn instantiate(
         nonce: 0,
     };
     SCHEDULES.save(deps.storage, &schedules)?;
+    PENDING_BATCH.save(deps.storage, &vec![])?;
+    BATCH_DUST.save(deps.storage, &Uint128::zero())?;
 
     Ok(Response::new()
         .add_attribute("action", "instantiate")
         .add_attributes([
             attr("owner", config.owner.to_string()),
-            attr("max_blocks_stale", config.max_blocks_old.to_string()),
+            attr(
+                "max_blocks_stale",
+                config.oracle_policy.max_blocks_old.to_string(),
+            ),
+            attr(
+                "max_seconds_stale",
+                config.oracle_policy.max_seconds_old.to_string(),
+            ),
             attr("denom_ntrn", pairs.denom_ntrn),
             attr("denom_usdc", pairs.pair_id),
             attr("pool_id", id.clone()),
@@ -97,9 +143,46 @@ pub fn execute(
         ExecuteMsg::DepositDca {
             max_sell_amount,
             max_slippage_basis_points,
-        } => deposit_dca(deps, _env, info, max_sell_amount, max_slippage_basis_points),
-        ExecuteMsg::RunSchedules { .. } => run_schedules(deps, _env),
+            order_type,
+            max_average_slippage_basis_points,
+            target_denom,
+            side,
+            slippage_ladder,
+            tranche_ladder,
+        } => deposit_dca(
+            deps,
+            _env,
+            info,
+            DepositParams {
+                max_sell_amount,
+                max_slippage_basis_points,
+                order_type: order_type.unwrap_or(ScheduleOrderType::ImmediateOrCancel),
+                max_average_slippage_basis_points,
+                target_denom,
+                side: side.unwrap_or(ScheduleSide::Buy),
+                slippage_ladder: slippage_ladder.unwrap_or_default(),
+                tranche_ladder: tranche_ladder.unwrap_or_default(),
+            },
+        ),
+        ExecuteMsg::RunSchedules { .. } => run_schedules(deps, _env, None),
+        ExecuteMsg::RunMySchedules { .. } => run_schedules(deps, _env, Some(info.sender)),
         ExecuteMsg::WithdrawAll { .. } => withdraw_all(deps, _env, info),
+        ExecuteMsg::ResumeSchedule { schedule_id } => {
+            resume_schedule(deps, _env, info, schedule_id)
+        }
+        ExecuteMsg::RegisterAsset { asset } => register_asset(deps, _env, info, asset),
+        ExecuteMsg::SetAssetEnabled { denom, enabled } => {
+            set_asset_enabled(deps, _env, info, denom, enabled)
+        }
+        ExecuteMsg::RegisterDexPair { denom_a, denom_b } => {
+            register_dex_pair(deps, _env, info, denom_a, denom_b)
+        }
+        ExecuteMsg::RemoveDexPair { denom_a, denom_b } => {
+            remove_dex_pair(deps, _env, info, denom_a, denom_b)
+        }
+        ExecuteMsg::SetRoute { target_denom, hops } => {
+            set_route(deps, _env, info, target_denom, hops)
+        }
     }
 }
 
@@ -112,6 +195,8 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> ContractResult<Binary> {
     match msg {
         QueryMsg::GetFormated {} => query_recent_valid_prices_formatted(deps, _env),
         QueryMsg::GetSchedules { address } => get_schedules(deps, _env, &address),
+        QueryMsg::GetAssets {} => get_assets(deps, _env),
+        QueryMsg::GetRoute { target_denom } => get_route(deps, _env, target_denom),
     }
 }
 
@@ -121,7 +206,17 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> ContractResult<Binary> {
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
-    match msg.id {
-        id => handle_run_schedule_reply(deps, env, msg.result, id),
+    match ReplyPayload::from_reply(&msg)? {
+        ReplyPayload::RunSchedule { order, ladder } => {
+            handle_run_schedule_reply(deps, env, msg.result, order, ladder)
+        }
+        ReplyPayload::VenueSwap { swap } => handle_venue_swap_reply(deps, env, msg.result, swap),
+        ReplyPayload::Batch {} => handle_batch_reply(deps, env, msg.result),
+        ReplyPayload::WithdrawFilled { op } => {
+            handle_withdraw_filled_reply(deps, env, msg.result, op)
+        }
+        ReplyPayload::CancelLimitOrder { op } => {
+            handle_cancel_limit_order_reply(deps, env, msg.result, op)
+        }
     }
 }
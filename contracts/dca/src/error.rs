@@ -1,4 +1,4 @@
-use cosmwasm_std::{OverflowError, StdError, Uint128};
+use cosmwasm_std::{Decimal, OverflowError, StdError, Uint128};
 use thiserror::Error;
 
 pub type ContractResult<T> = core::result::Result<T, ContractError>;
@@ -35,6 +35,16 @@ pub enum ContractError {
     #[error("denom {denom} is not a correct IBC denom: {reason}")]
     InvalidIbcDenom { denom: String, reason: String },
 
+    #[error("denom {denom} is not a valid denom: {reason}")]
+    InvalidDenom { denom: String, reason: String },
+
+    #[error("denom {denom} traces to {actual}, expected {expected}")]
+    IbcTraceMismatch {
+        denom: String,
+        expected: String,
+        actual: String,
+    },
+
     #[error("base: {base} quote: {quote} is not a valid currency pair: {reason}")]
     InvalidCurrencyPair {
         base: String,
@@ -77,6 +87,33 @@ pub enum ContractError {
         max_blocks: u64,
     },
 
+    #[error("Market {symbol}, {quote} is older than {max_seconds} seconds")]
+    PriceTooOldSeconds {
+        symbol: String,
+        quote: String,
+        max_seconds: u64,
+    },
+
+    #[error("Market {symbol}, {quote} has {providers} providers, at least {required} required")]
+    InsufficientProviders {
+        symbol: String,
+        quote: String,
+        providers: u64,
+        required: u64,
+    },
+
+    #[error("No route from {from} to {to}")]
+    RouteNotFound { from: String, to: String },
+
+    #[error("Invalid route: {reason}")]
+    InvalidRoute { reason: String },
+
+    #[error("Invalid order configuration: {reason}")]
+    InvalidOrderConfig { reason: String },
+
+    #[error("Reply {id} has no known payload")]
+    UnknownReply { id: u64 },
+
     #[error("input for {input} is invalid: {reason}")]
     MalformedInput { input: String, reason: String },
 
@@ -133,4 +170,28 @@ pub enum ContractError {
 
     #[error("Liquidity exists but cannot be retreived")]
     LiquidityNotFound,
+
+    #[error("DEX price {dex_price} deviates {deviation_bps} bps from oracle price {oracle_price}, max allowed is {max_bps} bps")]
+    PriceDeviationTooHigh {
+        oracle_price: Decimal,
+        dex_price: Decimal,
+        deviation_bps: u64,
+        max_bps: u64,
+    },
+
+    #[error("Asset {denom} is not registered")]
+    AssetNotRegistered { denom: String },
+
+    #[error("Asset {denom} is disabled")]
+    AssetDisabled { denom: String },
+
+    #[error("Only {available} price feeds returned a price, quorum is {required}")]
+    QuorumNotReached { available: u64, required: u64 },
+
+    #[error("DEX pool {pair_id} has {available} liquidity, at least {required} required")]
+    InsufficientDexLiquidity {
+        pair_id: String,
+        available: Uint128,
+        required: Uint128,
+    },
 }
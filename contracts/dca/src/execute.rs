@@ -1,22 +1,57 @@
 use crate::error::ContractError;
-use crate::state::{Schedule, CONFIG, SCHEDULES};
+use crate::oracle::get_run_price;
+use crate::reply::ReplyPayload;
+use crate::state::{
+    Asset, BatchPart, Config, LadderOrder, OpenTranche, PendingOrder, PendingTrancheOp,
+    PendingVenueSwap, PriceSource, Schedule, ScheduleOrderType, ScheduleSide, Schedules,
+    TrancheStep, ASSETS, BATCH_DUST, CONFIG, DEX_PAIRS, PENDING_BATCH, ROUTES, RUN_CAP_USED,
+    SCHEDULES, VENUE_FILLS,
+};
 use crate::utils::*;
+use crate::venue::{build_execution_venue, ExecutionVenue, SwapRequest, SwapResult};
 use cosmwasm_std::{
-    BankMsg, Coin, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response, SubMsg, SubMsgResult,
-    Uint128,
+    attr, Addr, Attribute, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, Event,
+    MessageInfo, Response, SubMsg, SubMsgResult, Uint128,
+};
+use neutron_std::types::neutron::dex::{
+    MsgCancelLimitOrder, MsgCancelLimitOrderResponse, MsgMultiHopSwap, MsgMultiHopSwapResponse,
+    MsgWithdrawFilledLimitOrder, MsgWithdrawFilledLimitOrderResponse, MultiHopRoute,
 };
-use neutron_std::types::neutron::dex::{LimitOrderType, MsgPlaceLimitOrder};
+use std::collections::BTreeMap;
+
+/// The schedule requested by a DepositDca message, with its defaults applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepositParams {
+    pub max_sell_amount: Uint128,
+    pub max_slippage_basis_points: u128,
+    pub order_type: ScheduleOrderType,
+    pub max_average_slippage_basis_points: Option<u128>,
+    // the denom to buy, denom_ntrn if None
+    pub target_denom: Option<String>,
+    pub side: ScheduleSide,
+    pub slippage_ladder: Vec<u128>,
+    pub tranche_ladder: Vec<TrancheStep>,
+}
 
 // Deposits a DCA schedule. Users can deposit multiple times to create multiple schedules
 // but there is a limit to the total number of schedules
-// Only allows DCA buys from USD to NTRN, so only USD_denom can be deposited
+// Buy schedules deposit USD_denom and sell schedules deposit NTRN_denom
 pub fn deposit_dca(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    max_sell_amount: Uint128,
-    max_slippage_basis_points: u128,
+    params: DepositParams,
 ) -> Result<Response, ContractError> {
+    let DepositParams {
+        max_sell_amount,
+        max_slippage_basis_points,
+        order_type,
+        max_average_slippage_basis_points,
+        target_denom,
+        side,
+        slippage_ladder,
+        tranche_ladder,
+    } = params;
     // Load the contract configuration from storage
     let config = CONFIG.load(deps.storage)?;
     // get the current user's schedule, default if no schedule found
@@ -39,11 +74,59 @@ pub fn deposit_dca(
         return Err(ContractError::MaxSchedulesReached {});
     }
 
-    // only allow usd to be sent. since we only allow DCA buys from USD to NTRN
-    if sent_funds[0].denom != config.pair_data.denom_usd {
+    // only allow the denom the schedule spends
+    if sent_funds[0].denom != deposit_denom(&config, &side) {
         return Err(ContractError::InvalidToken);
     }
 
+    validate_order_config(
+        &order_type,
+        max_slippage_basis_points,
+        max_average_slippage_basis_points,
+        &slippage_ladder,
+        &tranche_ladder,
+    )?;
+
+    // only venues with an order book can rest maker tranches
+    if order_type.is_maker()
+        && !build_execution_venue(&config.execution_venue).supports_maker_orders()
+    {
+        return Err(ContractError::InvalidOrderConfig {
+            reason: "the execution venue only supports taker orders".to_string(),
+        });
+    }
+
+    // anything but denom_ntrn is bought by a multi-hop swap, which must be routable and
+    // priceable through the registry. Multi-hop swaps are taker only and fill all or nothing
+    let target_denom = match side {
+        ScheduleSide::Buy => target_denom.unwrap_or(config.pair_data.denom_ntrn.clone()),
+        ScheduleSide::Sell => {
+            if target_denom.is_some_and(|denom| denom != config.pair_data.denom_usd) {
+                return Err(ContractError::InvalidOrderConfig {
+                    reason: "sell schedules can only buy denom_usd".to_string(),
+                });
+            }
+            config.pair_data.denom_usd.clone()
+        }
+    };
+    if side == ScheduleSide::Buy && target_denom != config.pair_data.denom_ntrn {
+        if !matches!(
+            order_type,
+            ScheduleOrderType::ImmediateOrCancel | ScheduleOrderType::FillOrKill
+        ) || max_average_slippage_basis_points.is_some()
+            || !slippage_ladder.is_empty()
+            || !tranche_ladder.is_empty()
+        {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "multi-hop schedules only support taker orders without average price \
+                         protection or ladders"
+                    .to_string(),
+            });
+        }
+        load_enabled_asset(deps.storage, &target_denom)?;
+        find_route(deps.storage, &config, &target_denom)?;
+    }
+
     // Create a new schedule for the user
     let new_schedule = Schedule {
         owner: info.sender.clone(),
@@ -51,6 +134,15 @@ pub fn deposit_dca(
         max_slippage_basis_points,
         remaining_amount: sent_funds[0].amount,
         id: schedules.nonce,
+        order_type,
+        max_average_slippage_basis_points,
+        open_tranches: vec![],
+        target_denom,
+        side,
+        slippage_ladder,
+        tranche_ladder,
+        consecutive_failures: 0,
+        parked: false,
     };
     schedules.schedules.push(new_schedule);
     schedules.nonce += 1;
@@ -71,73 +163,542 @@ pub fn to_dex_price(price: Decimal) -> Result<String, ContractError> {
         .to_string())
 }
 
-pub fn run_schedules(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+// Runs every schedule, or only the schedules of owner when it is set. A run of one owner's
+// schedules applies the same pricing and safety checks and leaves all other schedules as
+// they are
+pub fn run_schedules(
+    deps: DepsMut,
+    env: Env,
+    owner: Option<Addr>,
+) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     let mut schedules = SCHEDULES.load(deps.storage)?;
+    // the schedules outside the run are set aside and stored back unchanged
+    let mut untouched: Vec<Schedule> = vec![];
+    if let Some(owner) = &owner {
+        let (owned, others): (Vec<Schedule>, Vec<Schedule>) = schedules
+            .schedules
+            .into_iter()
+            .partition(|schedule| schedule.owner == *owner);
+        schedules.schedules = owned;
+        untouched = others;
+    }
 
     let mut submessages: Vec<SubMsg> = vec![];
     let mut schedules_to_remove: Vec<u128> = vec![];
 
-    // get the current slinky price and tick index.
-    let price = get_price(deps.as_ref(), env.clone())?;
+    // get the current slinky price, or the DEX price if slinky is unusable
+    let (price, price_source) = get_run_price(deps.as_ref(), env.clone())?;
+
+    // skip the run while denom_usd trades outside its peg. The pair is priced in the
+    // stablecoin's own ticker, never in USD, so the run price is already in denom_usd
+    let mut stable_attributes: Vec<Attribute> = vec![];
+    if let Some(depeg_guard) = &config.depeg_guard {
+        let stable_price = get_stable_price(deps.as_ref(), &env, &config)?;
+        stable_attributes.push(attr("stable_price", stable_price.to_string()));
+        if stable_price < depeg_guard.min_price || stable_price > depeg_guard.max_price {
+            return Ok(Response::new()
+                .add_attribute("action", "run_schedules_paused")
+                .add_attribute("reason", "stablecoin_depeg")
+                .add_attributes(stable_attributes));
+        }
+    }
+
+    // abort the run if the pool has drifted away from the oracle, the DEX price
+    // trivially agrees with itself so the check only applies to oracle prices
+    if let Some(max_bps) = config.max_dex_deviation_bps {
+        if price_source != PriceSource::Dex {
+            validate_dex_deviation(deps.as_ref(), &config, price, max_bps)?;
+        }
+    }
+
+    // the oracle price is in whole units, orders are placed in base units
+    let (ntrn_decimals, usd_decimals) = pair_decimals(deps.storage, &config)?;
+
+    let now = env.block.time.seconds();
+    // prices of multi-hop targets, queried once per run
+    let mut target_prices: BTreeMap<String, Decimal> = BTreeMap::new();
+    // venues that cannot be queried are treated as having no depth
+    let venue_depths: Vec<Uint128> = config
+        .venues
+        .iter()
+        .map(|venue| venue_depth(deps.as_ref(), venue).unwrap_or_default())
+        .collect();
+    let execution_venue = build_execution_venue(&config.execution_venue);
+    let mut schedules_skipped: u64 = 0;
+    let mut schedules_downsized: u64 = 0;
+    let mut batch_parts: Vec<BatchPart> = vec![];
+    let mut batch_limit_price: Option<Decimal> = None;
+    let mut batch_max_average_price: Option<Decimal> = None;
+
+    // schedules left with less than the DEX can fill are closed and refunded before any
+    // amount is netted or placed
+    let dust_refunds = close_dust_schedules(&config, &mut schedules, None);
+
+    let dues: Vec<Uint128> = schedules
+        .schedules
+        .iter()
+        .map(|schedule| due_amount(schedule, now))
+        .collect();
+
+    // buy and sell schedules trading the pair directly are crossed with each other at the
+    // run price first, so only the imbalance pays the DEX fees and spread. Maker schedules
+    // rest on the book at their own price and are left out
+    let price_base = to_base_unit_price(price, ntrn_decimals, usd_decimals)?;
+    let nettable: Vec<usize> = (0..schedules.schedules.len())
+        .filter(|&index| {
+            let schedule = &schedules.schedules[index];
+            !dues[index].is_zero()
+                && !schedule.order_type.is_maker()
+                && (schedule.side == ScheduleSide::Sell
+                    || schedule.target_denom == config.pair_data.denom_ntrn)
+        })
+        .collect();
+    let (buy_indices, sell_indices): (Vec<usize>, Vec<usize>) = nettable
+        .into_iter()
+        .partition(|&index| schedules.schedules[index].side == ScheduleSide::Buy);
+    let netting = net_intents(
+        &buy_indices
+            .iter()
+            .map(|&index| dues[index])
+            .collect::<Vec<Uint128>>(),
+        &sell_indices
+            .iter()
+            .map(|&index| dues[index])
+            .collect::<Vec<Uint128>>(),
+        price_base,
+    )?;
+    // the netted amounts are debited now and the proceeds sent to the owners directly
+    let mut netted = vec![Uint128::zero(); schedules.schedules.len()];
+    let mut netting_payouts: BTreeMap<(Addr, String), Uint128> = BTreeMap::new();
+    let buys = buy_indices
+        .iter()
+        .zip(netting.buy_debits.iter().zip(netting.buy_credits.iter()))
+        .map(|(index, amounts)| (index, amounts, &config.pair_data.denom_ntrn));
+    let sells = sell_indices
+        .iter()
+        .zip(netting.sell_debits.iter().zip(netting.sell_credits.iter()))
+        .map(|(index, amounts)| (index, amounts, &config.pair_data.denom_usd));
+    for (&index, (debit, credit), denom_out) in buys.chain(sells) {
+        let schedule = &mut schedules.schedules[index];
+        schedule.remaining_amount = schedule.remaining_amount.checked_sub(*debit)?;
+        netted[index] = *debit;
+        if !credit.is_zero() {
+            let payout = netting_payouts
+                .entry((schedule.owner.clone(), denom_out.clone()))
+                .or_default();
+            *payout = payout.checked_add(*credit)?;
+        }
+    }
+    let netting_messages: Vec<CosmosMsg> = netting_payouts
+        .into_iter()
+        .map(|((owner, denom), amount)| {
+            BankMsg::Send {
+                to_address: owner.to_string(),
+                amount: vec![Coin { denom, amount }],
+            }
+            .into()
+        })
+        .collect();
+
+    // the total sold by a run is capped to limit its market impact, sell schedules
+    // count with the denom_usd value of their denom_ntrn
+    let due_total = schedules.schedules.iter().enumerate().try_fold(
+        Uint128::zero(),
+        |total, (index, schedule)| -> Result<Uint128, ContractError> {
+            let residual = dues[index].checked_sub(netted[index])?;
+            let residual = match schedule.side {
+                ScheduleSide::Buy => residual,
+                ScheduleSide::Sell => residual.mul_floor(price_base),
+            };
+            Ok(total.checked_add(residual)?)
+        },
+    )?;
+    // the cap is shared by all runs of a block, so running schedules again in the same
+    // block only sells what the earlier runs left of it
+    let run_cap = match &config.run_cap {
+        Some(run_cap) => {
+            let cap = run_cap_amount(deps.as_ref(), &config, run_cap)?;
+            let used = match RUN_CAP_USED.may_load(deps.storage)? {
+                Some((height, used)) if height == env.block.height => used,
+                _ => Uint128::zero(),
+            };
+            let remaining = cap.saturating_sub(used);
+            let used = used.checked_add(remaining.min(due_total))?;
+            RUN_CAP_USED.save(deps.storage, &(env.block.height, used))?;
+            Some(remaining)
+        }
+        None => None,
+    };
+
+    // Loop over all schedules in a pseudo-random order, so the oldest schedules do not
+    // always take the best liquidity of the run
+    let order = shuffled_order(
+        schedules.schedules.len(),
+        run_seed(env.block.height, schedules.nonce),
+    );
+    for index in order {
+        let schedule = &mut schedules.schedules[index];
+        // collect the fills of expired maker tranches and cancel what is left of them
+        for tranche in schedule
+            .open_tranches
+            .iter()
+            .filter(|tranche| tranche.expiration <= now)
+        {
+            submessages.extend(withdraw_and_cancel_tranche(
+                &env,
+                schedule.id,
+                &schedule.owner,
+                &tranche.tranche_key,
+            )?);
+        }
 
-    // Loop over all schedules
-    for schedule in schedules.schedules.iter_mut() {
         let current_schedule_balance: Uint128 = schedule.remaining_amount;
         // Check if the current schedule balance is 0
         if current_schedule_balance.is_zero() {
-            // Mark this schedule for removal
-            schedules_to_remove.push(schedule.id);
+            // Mark this schedule for removal once its tranches are withdrawn
+            if schedule.open_tranches.is_empty() {
+                schedules_to_remove.push(schedule.id);
+            }
+            continue;
+        }
+
+        // parked schedules are not run until their owner resumes them
+        if schedule.parked {
+            continue;
+        }
+
+        // a maker schedule only rests one tranche on the book at a time
+        if schedule
+            .open_tranches
+            .iter()
+            .any(|tranche| tranche.expiration > now)
+        {
             continue;
         }
 
         // sell amount is the min of the current schedule balance and the max_sell_amount
-        let sell_amount = std::cmp::min(current_schedule_balance, schedule.max_sell_amount);
-        let (token_in, token_out) = (
-            config.pair_data.denom_usd.clone(),
-            config.pair_data.denom_ntrn.clone(),
-        );
+        // less what was netted, scaled down pro-rata if the run is capped below the total due
+        let sell_amount = dues[index].checked_sub(netted[index])?;
+        let sell_amount = match run_cap {
+            Some(run_cap) => pro_rata(sell_amount, run_cap, due_total),
+            None => sell_amount,
+        };
+        if sell_amount.is_zero() {
+            continue;
+        }
 
-        // the schedule_price is the price with the slippage_adjustment applied
-        let basis_point_adjustment =
-            price + Decimal::from_ratio(schedule.max_slippage_basis_points, 10000u128);
-        //increase target sell price by the basis point adjustment
-        let schedule_price = price + basis_point_adjustment;
-        // let target_tick_index = price_to_tick_index(schedule_price);
-        // 1.1 = 1100000000000000000
-
-        // place an IMMEDIATE_OR_CANCEL limit order. This will sell as much as it can at the price
-        // if the price changes before the order is filled the order will be cancelled
-        let msg_place_limit_order = Into::<CosmosMsg>::into(MsgPlaceLimitOrder {
-            creator: env.contract.address.to_string(),
-            receiver: schedule.owner.to_string(),
-            token_in: token_in.clone(),
-            token_out: token_out.clone(),
-            tick_index_in_to_out: 0,
-            amount_in: sell_amount.to_string(),
-            order_type: LimitOrderType::ImmediateOrCancel.into(),
-            expiration_time: None,
-            min_average_sell_price: None,
-            max_amount_out: None,
-            limit_sell_price: Some(to_dex_price(schedule_price)?),
-        });
+        // targets without a direct pool are bought by swapping along their route. The swap
+        // fills all or nothing within its exit limit price, so it is not simulated
+        if schedule.side == ScheduleSide::Buy
+            && schedule.target_denom != config.pair_data.denom_ntrn
+        {
+            let target_price = match target_prices.get(&schedule.target_denom) {
+                Some(target_price) => *target_price,
+                None => {
+                    let target_price =
+                        get_denom_price(deps.as_ref(), &env, &config, &schedule.target_denom)?;
+                    target_prices.insert(schedule.target_denom.clone(), target_price);
+                    target_price
+                }
+            };
+            let target_decimals =
+                load_enabled_asset(deps.storage, &schedule.target_denom)?.decimals;
+            let hops = find_route(deps.storage, &config, &schedule.target_denom)?;
+            let msg_multi_hop_swap = Into::<CosmosMsg>::into(MsgMultiHopSwap {
+                creator: env.contract.address.to_string(),
+                receiver: schedule.owner.to_string(),
+                routes: vec![MultiHopRoute { hops }],
+                amount_in: sell_amount.to_string(),
+                exit_limit_price: to_dex_price(exit_limit_price(
+                    target_price,
+                    schedule.max_slippage_basis_points,
+                    usd_decimals,
+                    target_decimals,
+                )?)?,
+                pick_best_route: false,
+            });
+            // tracked like a single venue part so the reply debits the amount swapped
+            let payload = ReplyPayload::VenueSwap {
+                swap: PendingVenueSwap {
+                    schedule_id: schedule.id,
+                    amount_in: sell_amount,
+                    multi_hop: true,
+                    last_part: true,
+                },
+            };
+            submessages.push(payload.sub_msg(msg_multi_hop_swap)?);
+            continue;
+        }
+
+        // maker orders rest at the oracle price, taker orders may cross up to the slippage
+        let slippage_basis_points = if schedule.order_type.is_maker() {
+            0
+        } else {
+            schedule.max_slippage_basis_points
+        };
+        let mut request = schedule_request(
+            &config,
+            schedule,
+            price,
+            slippage_basis_points,
+            sell_amount,
+            ntrn_decimals,
+            usd_decimals,
+        )?;
+
+        // a tranche ladder places one order per step at increasing prices, the fills are
+        // aggregated in the replies like venue parts. Each step expects a partial fill in a
+        // thin book so the liquidity check does not apply
+        if !schedule.tranche_ladder.is_empty() {
+            let weights: Vec<Uint128> = schedule
+                .tranche_ladder
+                .iter()
+                .map(|step| step.fraction.atomics())
+                .collect();
+            let parts = split_all(sell_amount, &weights)?;
+            let last_part = parts.iter().rposition(|part| !part.is_zero());
+            for (i, (step, part)) in schedule.tranche_ladder.iter().zip(parts).enumerate() {
+                if part.is_zero() {
+                    continue;
+                }
+                let step_request = schedule_request(
+                    &config,
+                    schedule,
+                    price,
+                    step.slippage_basis_points,
+                    part,
+                    ntrn_decimals,
+                    usd_decimals,
+                )?;
+                let payload = ReplyPayload::VenueSwap {
+                    swap: PendingVenueSwap {
+                        schedule_id: schedule.id,
+                        amount_in: part,
+                        multi_hop: false,
+                        last_part: Some(i) == last_part,
+                    },
+                };
+                submessages.push(payload.sub_msg(execution_venue.swap_msg(&env, &step_request)?)?);
+            }
+            continue;
+        }
+
+        // in batch mode ImmediateOrCancel schedules join one order at the tightest limits
+        // of the batch, the proceeds are split between them in the reply. Laddered schedules
+        // are placed on their own so their ladder applies
+        if config.batch_mode
+            && schedule.side == ScheduleSide::Buy
+            && matches!(schedule.order_type, ScheduleOrderType::ImmediateOrCancel)
+            && schedule.slippage_ladder.is_empty()
+        {
+            batch_parts.push(BatchPart {
+                schedule_id: schedule.id,
+                owner: schedule.owner.clone(),
+                amount_in: sell_amount,
+            });
+            batch_limit_price = Some(
+                batch_limit_price
+                    .map_or(request.limit_price, |limit| limit.min(request.limit_price)),
+            );
+            if let Some(max_average_price) = request.max_average_price {
+                batch_max_average_price = Some(
+                    batch_max_average_price
+                        .map_or(max_average_price, |average| average.min(max_average_price)),
+                );
+            }
+            continue;
+        }
+
+        // ImmediateOrCancel runs are split across the venues by depth, so no single pool
+        // takes all of the slippage. Each part is tracked and the fills aggregated in the replies.
+        // Laddered schedules are not split, like in batch mode
+        if !config.venues.is_empty()
+            && schedule.side == ScheduleSide::Buy
+            && matches!(schedule.order_type, ScheduleOrderType::ImmediateOrCancel)
+            && schedule.slippage_ladder.is_empty()
+        {
+            let split = split_by_depth(sell_amount, &venue_depths)?;
+            // limit order parts are simulated like a single order. Multi-hop parts swap all
+            // or nothing within their exit limit price and are placed without a simulation
+            let mut parts: Vec<Uint128> = vec![];
+            for (venue, part) in config.venues.iter().zip(split) {
+                if part.is_zero() || venue.hops.len() > 2 {
+                    parts.push(part);
+                    continue;
+                }
+                let part_request = SwapRequest {
+                    amount_in: part,
+                    ..request.clone()
+                };
+                parts.push(
+                    liquidity_checked_amount(
+                        deps.as_ref(),
+                        &env,
+                        &config,
+                        &*execution_venue,
+                        &part_request,
+                    )
+                    .unwrap_or_default(),
+                );
+            }
+            let placed = parts
+                .iter()
+                .try_fold(Uint128::zero(), |total, part| total.checked_add(*part))?;
+            if placed.is_zero() {
+                schedules_skipped += 1;
+                continue;
+            }
+            if placed < sell_amount {
+                schedules_downsized += 1;
+            }
+            let last_part = parts.iter().rposition(|part| !part.is_zero());
+            for (i, (venue, part)) in config.venues.iter().zip(parts).enumerate() {
+                if part.is_zero() {
+                    continue;
+                }
+                // the pair's own pool is traded on the configured execution venue
+                let multi_hop = venue.hops.len() > 2;
+                let msg = if multi_hop {
+                    Into::<CosmosMsg>::into(MsgMultiHopSwap {
+                        creator: env.contract.address.to_string(),
+                        receiver: schedule.owner.to_string(),
+                        routes: vec![MultiHopRoute {
+                            hops: venue.hops.clone(),
+                        }],
+                        amount_in: part.to_string(),
+                        exit_limit_price: to_dex_price(exit_limit_price(
+                            price,
+                            slippage_basis_points,
+                            usd_decimals,
+                            ntrn_decimals,
+                        )?)?,
+                        pick_best_route: false,
+                    })
+                } else {
+                    execution_venue.swap_msg(
+                        &env,
+                        &SwapRequest {
+                            amount_in: part,
+                            ..request.clone()
+                        },
+                    )?
+                };
+                let payload = ReplyPayload::VenueSwap {
+                    swap: PendingVenueSwap {
+                        schedule_id: schedule.id,
+                        amount_in: part,
+                        multi_hop,
+                        last_part: Some(i) == last_part,
+                    },
+                };
+                submessages.push(payload.sub_msg(msg)?);
+            }
+            continue;
+        }
+
+        match liquidity_checked_amount(deps.as_ref(), &env, &config, &*execution_venue, &request) {
+            Some(amount_in) => {
+                if amount_in < sell_amount {
+                    schedules_downsized += 1;
+                }
+                request.amount_in = amount_in;
+            }
+            None => {
+                schedules_skipped += 1;
+                continue;
+            }
+        }
+
+        // laddered schedules are checked against their max slippage but start at the first
+        // step, the reply escalates the order while it fills nothing
+        let mut ladder: Option<LadderOrder> = None;
+        if let Some(first_step) = schedule.slippage_ladder.first() {
+            request = schedule_request(
+                &config,
+                schedule,
+                price,
+                *first_step,
+                request.amount_in,
+                ntrn_decimals,
+                usd_decimals,
+            )?;
+            ladder = Some(LadderOrder {
+                step: 0,
+                amount_in: request.amount_in,
+                price,
+            });
+        }
 
         // push SubMsg
-        submessages.push(SubMsg::reply_always(
-            msg_place_limit_order,
-            schedule.id as u64,
-        ));
+        let payload = ReplyPayload::RunSchedule {
+            order: pending_order(schedule, &request),
+            ladder,
+        };
+        submessages.push(payload.sub_msg(execution_venue.swap_msg(&env, &request)?)?);
+    }
+
+    // the batch is placed as one order whose proceeds are received by the contract
+    if let Some(limit_price) = batch_limit_price {
+        let batch_total = batch_parts
+            .iter()
+            .try_fold(Uint128::zero(), |total, part| {
+                total.checked_add(part.amount_in)
+            })?;
+        let mut request = SwapRequest {
+            receiver: env.contract.address.to_string(),
+            token_in: config.pair_data.denom_usd.clone(),
+            token_out: config.pair_data.denom_ntrn.clone(),
+            amount_in: batch_total,
+            limit_price,
+            max_average_price: batch_max_average_price,
+            order_type: ScheduleOrderType::ImmediateOrCancel,
+            decimals_in: usd_decimals,
+            decimals_out: ntrn_decimals,
+        };
+        match liquidity_checked_amount(deps.as_ref(), &env, &config, &*execution_venue, &request) {
+            Some(amount_in) => {
+                request.amount_in = amount_in;
+                PENDING_BATCH.save(deps.storage, &batch_parts)?;
+                submessages.push(
+                    ReplyPayload::Batch {}.sub_msg(execution_venue.swap_msg(&env, &request)?)?,
+                );
+            }
+            None => schedules_skipped += batch_parts.len() as u64,
+        }
     }
     // Remove marked schedules
     schedules
         .schedules
         .retain(|s| !schedules_to_remove.contains(&s.id));
+    // schedules are stored in the order they were deposited
+    schedules.schedules.extend(untouched);
+    schedules.schedules.sort_by_key(|s| s.id);
 
     // Save the updated config, Config not modified
     SCHEDULES.save(deps.storage, &schedules)?;
     Ok(Response::new()
+        .add_messages(netting_messages)
+        .add_attribute("schedules_closed_as_dust", dust_refunds.len().to_string())
+        .add_messages(dust_refunds)
         .add_submessages(submessages)
-        .add_attribute("action", "dex_deposit"))
+        .add_attribute("action", "dex_deposit")
+        .add_attribute(
+            "run_scope",
+            owner.map_or("all".to_string(), |owner| owner.to_string()),
+        )
+        .add_attribute("price", price.to_string())
+        .add_attribute("price_source", price_source.as_str())
+        .add_attribute("netted_usd", netting.matched_usd.to_string())
+        .add_attribute("netted_ntrn", netting.matched_ntrn.to_string())
+        .add_attribute("due_total", due_total.to_string())
+        .add_attribute(
+            "run_cap",
+            run_cap.map_or("none".to_string(), |run_cap| run_cap.to_string()),
+        )
+        .add_attribute("schedules_skipped", schedules_skipped.to_string())
+        .add_attribute("schedules_downsized", schedules_downsized.to_string())
+        .add_attributes(stable_attributes))
 }
 
 pub fn withdraw_all(
@@ -147,30 +708,52 @@ pub fn withdraw_all(
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     let mut schedules = SCHEDULES.load(deps.storage)?;
-    let mut amount_owed: Uint128 = Uint128::zero();
+    // amounts owed keyed by denom, buy schedules hold usd and sell schedules ntrn
+    let mut amounts_owed: BTreeMap<String, Uint128> = BTreeMap::new();
+    let mut submessages: Vec<SubMsg> = vec![];
 
-    // Use retain to remove schedules and calculate amount owed in one pass
-    schedules.schedules.retain(|schedule| {
-        if schedule.owner == info.sender {
-            amount_owed += schedule.remaining_amount;
-            false // Remove this schedule
-        } else {
-            true // Keep this schedule
+    // Remove the sender's schedules and calculate the amount owed
+    let (withdrawn, kept): (Vec<Schedule>, Vec<Schedule>) = schedules
+        .schedules
+        .into_iter()
+        .partition(|schedule| schedule.owner == info.sender);
+    schedules.schedules = kept;
+    for schedule in withdrawn.iter() {
+        let owed = amounts_owed
+            .entry(deposit_denom(&config, &schedule.side))
+            .or_default();
+        *owed = owed.checked_add(schedule.remaining_amount)?;
+        // pull any resting maker tranches, their proceeds are sent in the replies
+        for tranche in schedule.open_tranches.iter() {
+            submessages.extend(withdraw_and_cancel_tranche(
+                &_env,
+                schedule.id,
+                &schedule.owner,
+                &tranche.tranche_key,
+            )?);
         }
-    });
+    }
 
     // Save the updated schedules
     SCHEDULES.save(deps.storage, &schedules)?;
 
+    let coins_owed: Vec<Coin> = amounts_owed
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(denom, amount)| Coin { denom, amount })
+        .collect();
+    let amount_attribute = coins_owed
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+
     let mut messages: Vec<CosmosMsg> = vec![];
-    if !amount_owed.is_zero() {
+    if !coins_owed.is_empty() {
         messages.push(
             BankMsg::Send {
                 to_address: info.sender.to_string(),
-                amount: vec![Coin {
-                    denom: config.pair_data.denom_usd.clone(),
-                    amount: amount_owed,
-                }],
+                amount: coins_owed,
             }
             .into(),
         );
@@ -178,35 +761,897 @@ pub fn withdraw_all(
 
     Ok(Response::new()
         .add_messages(messages)
+        .add_submessages(submessages)
         .add_attribute("action", "withdraw")
         .add_attribute("beneficiary", info.sender.to_string())
-        .add_attribute("amount", amount_owed.to_string()))
+        .add_attribute("amount", amount_attribute))
 }
 
-pub fn handle_run_schedule_reply(
+// Resumes a parked schedule of the sender, its failures are counted from zero again
+pub fn resume_schedule(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    schedule_id: u128,
+) -> Result<Response, ContractError> {
+    let mut schedules = SCHEDULES.load(deps.storage)?;
+    let schedule = schedules
+        .schedules
+        .iter_mut()
+        .find(|s| s.id == schedule_id)
+        .ok_or(ContractError::ScheduleNotFound)?;
+    if schedule.owner != info.sender {
+        return Err(ContractError::Unauthorized);
+    }
+    schedule.parked = false;
+    schedule.consecutive_failures = 0;
+    SCHEDULES.save(deps.storage, &schedules)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "resume_schedule")
+        .add_attribute("schedule_id", schedule_id.to_string()))
+}
+
+// Adds or replaces an asset in the registry. Re-registering one of the pair's assets
+// rebuilds the pair so a new ticker takes effect immediately
+pub fn register_asset(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset: Asset,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized);
+    }
+    if asset.denom.is_empty() || asset.ticker.is_empty() {
+        return Err(ContractError::EmptyValue {
+            kind: "asset".to_string(),
+        });
+    }
+    validate_asset(&deps.as_ref(), &asset)?;
+    ASSETS.save(deps.storage, &asset.denom, &asset)?;
+
+    let denom_ntrn = config.pair_data.denom_ntrn.clone();
+    let denom_usd = config.pair_data.denom_usd.clone();
+    if asset.enabled && (asset.denom == denom_ntrn || asset.denom == denom_usd) {
+        config.pair_data = build_pair_data(
+            &deps.as_ref(),
+            &env,
+            &denom_ntrn,
+            &denom_usd,
+            &config.oracle_policy,
+        )?;
+        CONFIG.save(deps.storage, &config)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "register_asset")
+        .add_attribute("denom", asset.denom)
+        .add_attribute("ticker", asset.ticker)
+        .add_attribute("decimals", asset.decimals.to_string())
+        .add_attribute("enabled", asset.enabled.to_string()))
+}
+
+pub fn set_asset_enabled(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    denom: String,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized);
+    }
+    let mut asset = ASSETS.may_load(deps.storage, &denom)?.ok_or_else(|| {
+        ContractError::AssetNotRegistered {
+            denom: denom.clone(),
+        }
+    })?;
+    asset.enabled = enabled;
+    ASSETS.save(deps.storage, &denom, &asset)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_asset_enabled")
+        .add_attribute("denom", denom)
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+pub fn register_dex_pair(
     deps: DepsMut,
     _env: Env,
+    info: MessageInfo,
+    denom_a: String,
+    denom_b: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized);
+    }
+    validate_registered_denom(&deps.as_ref(), &denom_a)?;
+    validate_registered_denom(&deps.as_ref(), &denom_b)?;
+    if denom_a == denom_b {
+        return Err(ContractError::InvalidRoute {
+            reason: "pair denoms must differ".to_string(),
+        });
+    }
+    let pair_id = get_pair_id_str(&denom_a, &denom_b);
+    DEX_PAIRS.save(deps.storage, &pair_id, &(denom_a, denom_b))?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_dex_pair")
+        .add_attribute("pair_id", pair_id))
+}
+
+pub fn remove_dex_pair(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    denom_a: String,
+    denom_b: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized);
+    }
+    let pair_id = get_pair_id_str(&denom_a, &denom_b);
+    DEX_PAIRS.remove(deps.storage, &pair_id);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_dex_pair")
+        .add_attribute("pair_id", pair_id))
+}
+
+pub fn set_route(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    target_denom: String,
+    hops: Option<Vec<String>>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized);
+    }
+    match hops {
+        Some(hops) => {
+            validate_route(&hops, &config.pair_data.denom_usd, &target_denom)?;
+            for hop in hops.iter() {
+                validate_registered_denom(&deps.as_ref(), hop)?;
+            }
+            ROUTES.save(deps.storage, &target_denom, &hops)?;
+        }
+        None => ROUTES.remove(deps.storage, &target_denom),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_route")
+        .add_attribute("target_denom", target_denom))
+}
+
+pub fn handle_run_schedule_reply(
+    deps: DepsMut,
+    env: Env,
     msg_result: SubMsgResult,
-    schedule_id: u64,
+    order: PendingOrder,
+    ladder: Option<LadderOrder>,
 ) -> Result<Response, ContractError> {
+    let schedule_id = order.schedule_id;
     match msg_result {
         SubMsgResult::Ok(result) => {
-            let amount_in = extract_amount_in(&result)?;
+            let config = CONFIG.load(deps.storage)?;
+            let execution_venue = build_execution_venue(&config.execution_venue);
+            // a reply that cannot be decoded is counted as a failed order, the schedule is
+            // left as it was
+            let swap_result = match execution_venue.decode_reply(&result) {
+                Ok(swap_result) => swap_result,
+                Err(err) => {
+                    let response = Response::new()
+                        .add_attribute("action", "place_limit_order_reply_error")
+                        .add_attribute("error", err.to_string())
+                        .add_attribute("schedule_id", schedule_id.to_string());
+                    return record_order_outcome(
+                        deps,
+                        schedule_id,
+                        Some(OrderFailure::InvalidReply),
+                        response,
+                    );
+                }
+            };
 
-            let mut schedules = SCHEDULES.load(deps.storage)?;
+            // the contract receives the immediate fill of a maker order and forwards it
+            let mut forwarded: Vec<CosmosMsg> = vec![];
+            if order.maker && !swap_result.amount_out.is_zero() {
+                forwarded.push(
+                    BankMsg::Send {
+                        to_address: order.owner.to_string(),
+                        amount: vec![Coin {
+                            denom: order.token_out.clone(),
+                            amount: swap_result.amount_out,
+                        }],
+                    }
+                    .into(),
+                );
+            }
 
-            update_schedules(&mut schedules, schedule_id, amount_in)?;
+            let mut schedules = SCHEDULES.load(deps.storage)?;
+            let Some(schedule) = schedules.schedules.iter_mut().find(|s| s.id == schedule_id)
+            else {
+                return closed_schedule_order(&env, &order, swap_result, forwarded);
+            };
+            let requested = order.amount_in;
+            let taker_in = swap_result.amount_in;
+            // maker orders that fill nothing immediately rest on the book
+            let no_fill = taker_in.is_zero() && !schedule.order_type.is_maker();
+            // the fill is measured against the amount the order was placed with, which can be
+            // less than the schedule's due amount after netting, the run cap or downsizing
+            let fill = if taker_in >= requested {
+                "full"
+            } else if taker_in.is_zero() {
+                "none"
+            } else {
+                "partial"
+            };
+            let amount_in = match schedule.order_type {
+                // a partial fill only spends what was swapped, the rest stays in the schedule
+                ScheduleOrderType::ImmediateOrCancel => taker_in,
+                // a successful fill or kill order always swapped the whole amount
+                ScheduleOrderType::FillOrKill => taker_in,
+                // everything placed has left the contract, the part that did not fill as
+                // taker rests in the tranche and is credited back when it is cancelled
+                ScheduleOrderType::GoodTilTime { .. } | ScheduleOrderType::JustInTime => {
+                    let placed = swap_result.placed;
+                    let resting = placed.checked_sub(taker_in)?;
+                    if let Some(tranche_key) =
+                        swap_result.tranche_key.filter(|_| !resting.is_zero())
+                    {
+                        let now = env.block.time.seconds();
+                        let expiration = match schedule.order_type {
+                            ScheduleOrderType::GoodTilTime { expiry_seconds } => {
+                                now.saturating_add(expiry_seconds)
+                            }
+                            // just in time tranches are expired by the next block
+                            _ => now,
+                        };
+                        schedule.open_tranches.push(OpenTranche {
+                            tranche_key,
+                            amount: resting,
+                            expiration,
+                            withdrawn: false,
+                        });
+                    }
+                    placed
+                }
+            };
 
+            // a schedule left with dust is closed by the next run
+            let amount_in = debit_schedule(&mut schedules, schedule_id, amount_in)?;
             SCHEDULES.save(deps.storage, &schedules)?;
 
-            Ok(Response::new()
+            let response = Response::new()
+                .add_messages(forwarded)
                 .add_attribute("action", "place_limit_order_reply_success")
                 .add_attribute("schedule_id", schedule_id.to_string())
-                .add_attribute("amount_in", amount_in.to_string()))
+                .add_attribute("venue", execution_venue.name())
+                .add_attribute("amount_in", amount_in.unwrap_or_default().to_string())
+                .add_attribute("amount_out", swap_result.amount_out.to_string())
+                .add_attribute("taker_fill", fill);
+            // an order that filled nothing is retried at the next step of its ladder and
+            // only counts as a failure once the ladder is exhausted
+            if no_fill {
+                if let Some((msg, step)) =
+                    escalate_ladder(deps.as_ref(), &env, schedule_id, &ladder)?
+                {
+                    return Ok(response
+                        .add_submessage(msg)
+                        .add_attribute("ladder_step", step.to_string()));
+                }
+                return record_order_outcome(
+                    deps,
+                    schedule_id,
+                    Some(OrderFailure::NoFill),
+                    response,
+                );
+            }
+            // the step of the order that did fill is recorded
+            let response = match ladder {
+                Some(order) => response.add_attribute("ladder_step_filled", order.step.to_string()),
+                None => response,
+            };
+            record_order_outcome(deps, schedule_id, None, response)
+        }
+        SubMsgResult::Err(err) => {
+            let failure = classify_order_error(&err);
+            let response = Response::new()
+                .add_attribute("action", "place_limit_order_reply_error")
+                .add_attribute("error", err)
+                .add_attribute("schedule_id", schedule_id.to_string());
+            if let Some((msg, step)) = escalate_ladder(deps.as_ref(), &env, schedule_id, &ladder)? {
+                return Ok(response
+                    .add_submessage(msg)
+                    .add_attribute("ladder_step", step.to_string()));
+            }
+            record_order_outcome(deps, schedule_id, Some(failure), response)
+        }
+    }
+}
+
+// The reply of an order whose schedule was closed while it was in flight. Taker proceeds
+// were received by the owner, the immediate fill of a maker order is forwarded to them and
+// its resting tranche is withdrawn and cancelled right away, settling to the owner
+fn closed_schedule_order(
+    env: &Env,
+    order: &PendingOrder,
+    swap_result: SwapResult,
+    forwarded: Vec<CosmosMsg>,
+) -> Result<Response, ContractError> {
+    let mut submessages: Vec<SubMsg> = vec![];
+    let resting = swap_result.placed.saturating_sub(swap_result.amount_in);
+    if let Some(tranche_key) = swap_result
+        .tranche_key
+        .filter(|_| order.maker && !resting.is_zero())
+    {
+        submessages.extend(withdraw_and_cancel_tranche(
+            env,
+            order.schedule_id,
+            &order.owner,
+            &tranche_key,
+        )?);
+    }
+    Ok(Response::new()
+        .add_messages(forwarded)
+        .add_submessages(submessages)
+        .add_attribute("action", "place_limit_order_reply_success")
+        .add_attribute("schedule_id", order.schedule_id.to_string())
+        .add_attribute("schedule_closed", "true")
+        .add_attribute("amount_out", swap_result.amount_out.to_string()))
+}
+
+// Debits a schedule by what its order spent. The funds have already left the contract, so
+// a debit above what the schedule holds is capped instead of reverting the swap. Returns
+// the amount debited, None if the schedule was closed
+fn debit_schedule(
+    schedules: &mut Schedules,
+    schedule_id: u128,
+    amount_in: Uint128,
+) -> Result<Option<Uint128>, ContractError> {
+    match update_schedules(schedules, schedule_id, amount_in) {
+        Ok(()) => Ok(Some(amount_in)),
+        Err(ContractError::ScheduleNotFound) => Ok(None),
+        Err(ContractError::InsufficientLiquidity { requested, .. }) => {
+            update_schedules(schedules, schedule_id, requested)?;
+            Ok(Some(requested))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+// The order of a schedule the run reply is dispatched on
+fn pending_order(schedule: &Schedule, request: &SwapRequest) -> PendingOrder {
+    PendingOrder {
+        schedule_id: schedule.id,
+        owner: schedule.owner.clone(),
+        amount_in: request.amount_in,
+        token_out: request.token_out.clone(),
+        maker: schedule.order_type.is_maker(),
+    }
+}
+
+fn escalate_ladder(
+    deps: Deps,
+    env: &Env,
+    schedule_id: u128,
+    ladder: &Option<LadderOrder>,
+) -> Result<Option<(SubMsg, usize)>, ContractError> {
+    match ladder {
+        Some(order) => next_ladder_order(deps, env, schedule_id, order),
+        None => Ok(None),
+    }
+}
+
+// Counts a failed order of a schedule and parks the schedule once its orders failed
+// max_consecutive_failures runs in a row, the owner is notified by a schedule_parked
+// event. An order that fills resets the count
+fn record_order_outcome(
+    deps: DepsMut,
+    schedule_id: u128,
+    failure: Option<OrderFailure>,
+    response: Response,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut schedules = SCHEDULES.load(deps.storage)?;
+    // the fill may have closed the schedule
+    let Some(schedule) = schedules.schedules.iter_mut().find(|s| s.id == schedule_id) else {
+        return Ok(response);
+    };
+    let Some(failure) = failure else {
+        schedule.consecutive_failures = 0;
+        SCHEDULES.save(deps.storage, &schedules)?;
+        return Ok(response);
+    };
+
+    schedule.consecutive_failures += 1;
+    let mut response = response
+        .add_attribute("failure", failure.as_str())
+        .add_attribute(
+            "consecutive_failures",
+            schedule.consecutive_failures.to_string(),
+        );
+    if !schedule.parked
+        && config
+            .max_consecutive_failures
+            .is_some_and(|max| schedule.consecutive_failures >= max)
+    {
+        schedule.parked = true;
+        response = response.add_event(
+            Event::new("schedule_parked")
+                .add_attribute("schedule_id", schedule_id.to_string())
+                .add_attribute("owner", schedule.owner.to_string())
+                .add_attribute(
+                    "consecutive_failures",
+                    schedule.consecutive_failures.to_string(),
+                )
+                .add_attribute("failure", failure.as_str()),
+        );
+    }
+    SCHEDULES.save(deps.storage, &schedules)?;
+    Ok(response)
+}
+
+// The order of a schedule at price, in whole denom_usd per denom_ntrn. Sell schedules pay
+// denom_ntrn for denom_usd at the inverse of the price. The limit price is the price with
+// the slippage adjustment applied, taker orders can also bound the average price of the
+// whole fill
+fn schedule_request(
+    config: &Config,
+    schedule: &Schedule,
+    price: Decimal,
+    slippage_basis_points: u128,
+    amount_in: Uint128,
+    ntrn_decimals: u32,
+    usd_decimals: u32,
+) -> Result<SwapRequest, ContractError> {
+    let (token_in, token_out, price, decimals_in, decimals_out) = match schedule.side {
+        ScheduleSide::Buy => (
+            &config.pair_data.denom_usd,
+            &config.pair_data.denom_ntrn,
+            price,
+            usd_decimals,
+            ntrn_decimals,
+        ),
+        ScheduleSide::Sell => (
+            &config.pair_data.denom_ntrn,
+            &config.pair_data.denom_usd,
+            cross_price(Decimal::one(), price)?,
+            ntrn_decimals,
+            usd_decimals,
+        ),
+    };
+    Ok(SwapRequest {
+        receiver: schedule.owner.to_string(),
+        token_in: token_in.clone(),
+        token_out: token_out.clone(),
+        amount_in,
+        limit_price: apply_slippage(price, slippage_basis_points)?,
+        max_average_price: schedule
+            .max_average_slippage_basis_points
+            .map(|average_slippage_basis_points| {
+                apply_slippage(price, average_slippage_basis_points)
+            })
+            .transpose()?,
+        order_type: schedule.order_type.clone(),
+        decimals_in,
+        decimals_out,
+    })
+}
+
+// Places the order of a laddered schedule again at the next step of its ladder. Returns
+// None once the ladder is exhausted
+fn next_ladder_order(
+    deps: Deps,
+    env: &Env,
+    schedule_id: u128,
+    order: &LadderOrder,
+) -> Result<Option<(SubMsg, usize)>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let schedules = SCHEDULES.load(deps.storage)?;
+    let step = order.step + 1;
+    let next = schedules
+        .schedules
+        .iter()
+        .find(|s| s.id == schedule_id)
+        .and_then(|schedule| {
+            schedule
+                .slippage_ladder
+                .get(step)
+                .map(|slippage_basis_points| (schedule, *slippage_basis_points))
+        });
+    let Some((schedule, slippage_basis_points)) = next else {
+        return Ok(None);
+    };
+
+    let (ntrn_decimals, usd_decimals) = pair_decimals(deps.storage, &config)?;
+    let request = schedule_request(
+        &config,
+        schedule,
+        order.price,
+        slippage_basis_points,
+        std::cmp::min(order.amount_in, schedule.remaining_amount),
+        ntrn_decimals,
+        usd_decimals,
+    )?;
+    let payload = ReplyPayload::RunSchedule {
+        order: pending_order(schedule, &request),
+        ladder: Some(LadderOrder {
+            step,
+            amount_in: request.amount_in,
+            price: order.price,
+        }),
+    };
+    let msg = build_execution_venue(&config.execution_venue).swap_msg(env, &request)?;
+    Ok(Some((payload.sub_msg(msg)?, step)))
+}
+
+// Taker orders are simulated first so thin books are skipped or downsized instead of
+// failing in the reply. Returns the amount to place, None to skip the order. A failed
+// simulation counts as no fill
+fn liquidity_checked_amount(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    execution_venue: &dyn ExecutionVenue,
+    request: &SwapRequest,
+) -> Option<Uint128> {
+    match &config.liquidity_check {
+        Some(liquidity_check) if !request.order_type.is_maker() => {
+            let expected_fill = execution_venue
+                .simulate_fill(deps, env, request)
+                .unwrap_or_default();
+            checked_fill_amount(expected_fill, request.amount_in, liquidity_check)
+        }
+        _ => Some(request.amount_in),
+    }
+}
+
+// Splits the fill of a batch order between its schedules in proportion to what they put
+// in. The denom_ntrn that does not divide evenly is carried forward to the next batch.
+// A failed batch, or one whose reply cannot be decoded, counts as a failure of each of
+// its schedules
+pub fn handle_batch_reply(
+    mut deps: DepsMut,
+    _env: Env,
+    msg_result: SubMsgResult,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let parts = PENDING_BATCH.load(deps.storage)?;
+    PENDING_BATCH.save(deps.storage, &vec![])?;
+
+    let decoded = match msg_result {
+        SubMsgResult::Ok(result) => build_execution_venue(&config.execution_venue)
+            .decode_reply(&result)
+            .map_err(|err| (OrderFailure::InvalidReply, err.to_string())),
+        SubMsgResult::Err(err) => Err((classify_order_error(&err), err)),
+    };
+    let swap_result = match decoded {
+        Ok(swap_result) => swap_result,
+        Err((failure, err)) => {
+            let mut response = Response::new()
+                .add_attribute("action", "batch_reply_error")
+                .add_attribute("error", err)
+                .add_attribute("schedules", parts.len().to_string());
+            for part in parts.iter() {
+                response =
+                    record_order_outcome(deps.branch(), part.schedule_id, Some(failure), response)?;
+            }
+            return Ok(response);
+        }
+    };
+
+    let inputs: Vec<Uint128> = parts.iter().map(|part| part.amount_in).collect();
+    let debits = split_exact(swap_result.amount_in, &inputs)?;
+    let carried_dust = BATCH_DUST.may_load(deps.storage)?.unwrap_or_default();
+    let (payouts, dust) =
+        split_pro_rata(swap_result.amount_out.checked_add(carried_dust)?, &inputs)?;
+    BATCH_DUST.save(deps.storage, &dust)?;
+
+    let mut schedules = SCHEDULES.load(deps.storage)?;
+    let mut owed: BTreeMap<String, Uint128> = BTreeMap::new();
+    // the payouts go to the owners whether or not their schedules are still open
+    for ((part, debit), payout) in parts.iter().zip(debits.iter()).zip(payouts) {
+        if !debit.is_zero() {
+            debit_schedule(&mut schedules, part.schedule_id, *debit)?;
+        }
+        let total = owed.entry(part.owner.to_string()).or_default();
+        *total = total.checked_add(payout)?;
+    }
+    let schedule_ids: Vec<u128> = parts.iter().map(|part| part.schedule_id).collect();
+    let dust_refunds = close_dust_schedules(&config, &mut schedules, Some(&schedule_ids));
+    SCHEDULES.save(deps.storage, &schedules)?;
+
+    let messages: Vec<CosmosMsg> = owed
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(owner, amount)| {
+            BankMsg::Send {
+                to_address: owner,
+                amount: vec![Coin {
+                    denom: config.pair_data.denom_ntrn.clone(),
+                    amount,
+                }],
+            }
+            .into()
+        })
+        .collect();
+
+    let mut response = Response::new()
+        .add_messages(messages)
+        .add_messages(dust_refunds)
+        .add_attribute("action", "batch_reply_success")
+        .add_attribute("schedules", parts.len().to_string())
+        .add_attribute("amount_in", swap_result.amount_in.to_string())
+        .add_attribute("amount_out", swap_result.amount_out.to_string())
+        .add_attribute("dust", dust.to_string());
+    // a schedule whose share of the batch filled nothing failed its run
+    for (part, debit) in parts.iter().zip(debits) {
+        let failure = debit.is_zero().then_some(OrderFailure::NoFill);
+        response = record_order_outcome(deps.branch(), part.schedule_id, failure, response)?;
+    }
+    Ok(response)
+}
+
+// Debits a schedule by what one of its venue parts swapped. The reply of the last part
+// reports the fill aggregated over all of the schedule's venues in the run
+pub fn handle_venue_swap_reply(
+    deps: DepsMut,
+    _env: Env,
+    msg_result: SubMsgResult,
+    swap: PendingVenueSwap,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new()
+        .add_attribute("schedule_id", swap.schedule_id.to_string())
+        .add_attribute("venue_amount", swap.amount_in.to_string());
+    let mut failure: Option<OrderFailure> = None;
+    let (amount_in, amount_out) = match msg_result {
+        // a successful multi-hop swap always swaps the whole part
+        SubMsgResult::Ok(result) => {
+            let decoded = if swap.multi_hop {
+                decode_response::<MsgMultiHopSwapResponse>(&result)
+                    .and_then(|swap_response| to_coin(swap_response.coin_out))
+                    .map(|coin_out| (swap.amount_in, coin_amount(coin_out)))
+            } else {
+                build_execution_venue(&config.execution_venue)
+                    .decode_reply(&result)
+                    .map(|swap_result| (swap_result.amount_in, swap_result.amount_out))
+            };
+            match decoded {
+                Ok(amounts) => {
+                    response = response.add_attribute("action", "venue_swap_reply_success");
+                    amounts
+                }
+                // a part whose reply cannot be decoded counts as a failed part
+                Err(err) => {
+                    failure = Some(OrderFailure::InvalidReply);
+                    response = response
+                        .add_attribute("action", "venue_swap_reply_error")
+                        .add_attribute("error", err.to_string());
+                    (Uint128::zero(), Uint128::zero())
+                }
+            }
+        }
+        SubMsgResult::Err(err) => {
+            failure = Some(classify_order_error(&err));
+            response = response
+                .add_attribute("action", "venue_swap_reply_error")
+                .add_attribute("error", err);
+            (Uint128::zero(), Uint128::zero())
+        }
+    };
+
+    // the proceeds of a venue part are received by the owner, a closed schedule is not debited
+    if !amount_in.is_zero() {
+        let mut schedules = SCHEDULES.load(deps.storage)?;
+        if debit_schedule(&mut schedules, swap.schedule_id, amount_in)?.is_none() {
+            response = response.add_attribute("schedule_closed", "true");
+        }
+        SCHEDULES.save(deps.storage, &schedules)?;
+    }
+
+    let (total_in, total_out) = VENUE_FILLS
+        .may_load(deps.storage, swap.schedule_id)?
+        .unwrap_or_default();
+    let total_in = total_in.checked_add(amount_in)?;
+    let total_out = total_out.checked_add(amount_out)?;
+    let response = response
+        .add_attribute("amount_in", amount_in.to_string())
+        .add_attribute("amount_out", amount_out.to_string());
+    if !swap.last_part {
+        VENUE_FILLS.save(deps.storage, swap.schedule_id, &(total_in, total_out))?;
+        return Ok(response);
+    }
+
+    // the schedule's run only failed if none of its parts filled. Its dust is only closed
+    // once no part of it is in flight
+    VENUE_FILLS.remove(deps.storage, swap.schedule_id);
+    let mut schedules = SCHEDULES.load(deps.storage)?;
+    let dust_refunds = close_dust_schedules(&config, &mut schedules, Some(&[swap.schedule_id]));
+    SCHEDULES.save(deps.storage, &schedules)?;
+    let response = response.add_messages(dust_refunds).add_attributes([
+        attr("total_amount_in", total_in.to_string()),
+        attr("total_amount_out", total_out.to_string()),
+    ]);
+    let failure = match total_in.is_zero() {
+        true => Some(failure.unwrap_or(OrderFailure::NoFill)),
+        false => None,
+    };
+    record_order_outcome(deps, swap.schedule_id, failure, response)
+}
+
+// Withdraws the filled part of a maker tranche and cancels the unfilled part.
+// Both replies carry the tranche so the proceeds can be attributed to the schedule
+fn withdraw_and_cancel_tranche(
+    env: &Env,
+    schedule_id: u128,
+    owner: &Addr,
+    tranche_key: &str,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let op = PendingTrancheOp {
+        schedule_id,
+        owner: owner.clone(),
+        tranche_key: tranche_key.to_string(),
+    };
+
+    Ok(vec![
+        ReplyPayload::WithdrawFilled { op: op.clone() }.sub_msg(MsgWithdrawFilledLimitOrder {
+            creator: env.contract.address.to_string(),
+            tranche_key: tranche_key.to_string(),
+        })?,
+        ReplyPayload::CancelLimitOrder { op }.sub_msg(MsgCancelLimitOrder {
+            creator: env.contract.address.to_string(),
+            tranche_key: tranche_key.to_string(),
+        })?,
+    ])
+}
+
+pub fn handle_withdraw_filled_reply(
+    deps: DepsMut,
+    _env: Env,
+    msg_result: SubMsgResult,
+    op: PendingTrancheOp,
+) -> Result<Response, ContractError> {
+    match msg_result {
+        SubMsgResult::Ok(result) => {
+            let response = decode_response::<MsgWithdrawFilledLimitOrderResponse>(&result)?;
+            settle_tranche_proceeds(
+                deps,
+                &op,
+                to_coin(response.taker_coin_out)?,
+                to_coin(response.maker_coin_out)?,
+                false,
+            )
+            .map(|response| response.add_attribute("action", "withdraw_filled_reply_success"))
         }
         SubMsgResult::Err(err) => Ok(Response::new()
-            .add_attribute("action", "place_limit_order_reply_error")
+            .add_attribute("action", "withdraw_filled_reply_error")
             .add_attribute("error", err)
-            .add_attribute("schedule_id", schedule_id.to_string())),
+            .add_attribute("schedule_id", op.schedule_id.to_string())
+            .add_attribute("tranche_key", op.tranche_key)),
+    }
+}
+
+pub fn handle_cancel_limit_order_reply(
+    deps: DepsMut,
+    _env: Env,
+    msg_result: SubMsgResult,
+    op: PendingTrancheOp,
+) -> Result<Response, ContractError> {
+    match msg_result {
+        SubMsgResult::Ok(result) => {
+            let response = decode_response::<MsgCancelLimitOrderResponse>(&result)?;
+            settle_tranche_proceeds(
+                deps,
+                &op,
+                to_coin(response.taker_coin_out)?,
+                to_coin(response.maker_coin_out)?,
+                true,
+            )
+            .map(|response| response.add_attribute("action", "cancel_limit_order_reply_success"))
+        }
+        // nothing was left to cancel if the withdraw before it succeeded, otherwise the
+        // tranche stays tracked and is withdrawn and cancelled again by the next run
+        SubMsgResult::Err(err) => {
+            let withdrawn = SCHEDULES
+                .load(deps.storage)?
+                .schedules
+                .iter()
+                .find(|s| s.id == op.schedule_id)
+                .and_then(|schedule| {
+                    schedule
+                        .open_tranches
+                        .iter()
+                        .find(|tranche| tranche.tranche_key == op.tranche_key)
+                })
+                .is_some_and(|tranche| tranche.withdrawn);
+            let response = match withdrawn {
+                true => settle_tranche_proceeds(deps, &op, None, None, true)?,
+                false => Response::new()
+                    .add_attribute("schedule_id", op.schedule_id.to_string())
+                    .add_attribute("tranche_key", op.tranche_key.clone()),
+            };
+            Ok(response
+                .add_attribute("action", "cancel_limit_order_reply_error")
+                .add_attribute("error", err)
+                .add_attribute("tranche_closed", withdrawn.to_string()))
+        }
+    }
+}
+
+// Sends the filled tokens of a tranche to its owner and credits the unfilled deposit
+// back to the schedule, or to the owner if the schedule was withdrawn
+fn settle_tranche_proceeds(
+    deps: DepsMut,
+    op: &PendingTrancheOp,
+    taker_coin_out: Option<Coin>,
+    maker_coin_out: Option<Coin>,
+    close_tranche: bool,
+) -> Result<Response, ContractError> {
+    let mut schedules = SCHEDULES.load(deps.storage)?;
+    let mut payouts: Vec<Coin> = vec![];
+    if let Some(coin) = taker_coin_out.clone() {
+        payouts.push(coin);
     }
+
+    match schedules
+        .schedules
+        .iter_mut()
+        .find(|s| s.id == op.schedule_id)
+    {
+        Some(schedule) => {
+            if let Some(coin) = maker_coin_out.clone() {
+                schedule.remaining_amount = schedule.remaining_amount.checked_add(coin.amount)?;
+            }
+            if close_tranche {
+                schedule
+                    .open_tranches
+                    .retain(|tranche| tranche.tranche_key != op.tranche_key);
+            } else if let Some(tranche) = schedule
+                .open_tranches
+                .iter_mut()
+                .find(|tranche| tranche.tranche_key == op.tranche_key)
+            {
+                tranche.withdrawn = true;
+            }
+        }
+        None => payouts.extend(maker_coin_out.clone()),
+    }
+    // drop schedules that are fully spent now that their last tranche is closed
+    schedules
+        .schedules
+        .retain(|s| !(s.remaining_amount.is_zero() && s.open_tranches.is_empty()));
+    let dust_refunds = close_dust_schedules(
+        &CONFIG.load(deps.storage)?,
+        &mut schedules,
+        Some(&[op.schedule_id]),
+    );
+    SCHEDULES.save(deps.storage, &schedules)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !payouts.is_empty() {
+        messages.push(
+            BankMsg::Send {
+                to_address: op.owner.to_string(),
+                amount: payouts,
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_messages(dust_refunds)
+        .add_attribute("schedule_id", op.schedule_id.to_string())
+        .add_attribute("tranche_key", op.tranche_key.clone())
+        .add_attribute("amount_out", coin_amount(taker_coin_out).to_string())
+        .add_attribute("amount_refunded", coin_amount(maker_coin_out).to_string()))
 }
@@ -2,10 +2,29 @@ pub mod contract;
 pub mod error;
 pub mod execute;
 pub mod msg;
+pub mod oracle;
 pub mod query;
+pub mod reply;
 pub mod state;
 pub mod utils;
+pub mod venue;
 
 #[cfg(test)]
 #[path = "./tests/utils_tests.rs"]
 pub mod utils_tests;
+
+#[cfg(test)]
+#[path = "./tests/oracle_tests.rs"]
+pub mod oracle_tests;
+
+#[cfg(test)]
+#[path = "./tests/venue_tests.rs"]
+pub mod venue_tests;
+
+#[cfg(test)]
+#[path = "./tests/reply_tests.rs"]
+pub mod reply_tests;
+
+#[cfg(test)]
+#[path = "./tests/contract_tests.rs"]
+pub mod contract_tests;
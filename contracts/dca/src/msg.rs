@@ -1,6 +1,11 @@
 use crate::error::{ContractError, ContractResult};
+use crate::state::{
+    Asset, DepegGuard, DexFallback, ExecutionVenueConfig, LiquidityCheck, OraclePolicy,
+    PriceAggregation, PriceFeedConfig, RunCap, ScheduleOrderType, ScheduleSide, TrancheStep, Venue,
+};
+use crate::utils::{validate_denom_format, validate_route};
 use cosmwasm_std::Addr;
-use cosmwasm_std::{Coin, Decimal, Response, Uint128};
+use cosmwasm_std::{Coin, Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -25,7 +30,24 @@ pub struct InstantiateMsg {
     pub owner: String,
     pub denom_ntrn: String,
     pub denom_usd: String,
-    pub max_block_old: u64,
+    // the initial asset registry, must contain denom_ntrn and denom_usd
+    pub assets: Vec<Asset>,
+    pub oracle_policy: OraclePolicy,
+    pub dex_fallback: Option<DexFallback>,
+    pub max_dex_deviation_bps: Option<u64>,
+    pub price_aggregation: Option<PriceAggregation>,
+    pub depeg_guard: Option<DepegGuard>,
+    // the venues runs are split across, defaults to a single order on the pair's pool
+    pub venues: Option<Vec<Venue>>,
+    // defaults to the Neutron DEX
+    pub execution_venue: Option<ExecutionVenueConfig>,
+    pub liquidity_check: Option<LiquidityCheck>,
+    pub run_cap: Option<RunCap>,
+    // defaults to false
+    pub batch_mode: Option<bool>,
+    pub max_consecutive_failures: Option<u64>,
+    // per denom_ntrn and denom_usd, defaults to none
+    pub min_tradable_amounts: Option<Vec<Coin>>,
     pub max_schedules: u64,
 }
 
@@ -34,49 +56,132 @@ impl InstantiateMsg {
         self.check_empty(self.owner.clone(), "beneficiary".to_string())?;
         self.check_empty(self.denom_ntrn.clone(), "ntrn".to_string())?;
         self.check_empty(self.denom_usd.clone(), "usd".to_string())?;
+        for asset in self.assets.iter() {
+            self.check_empty(asset.denom.clone(), "asset denom".to_string())?;
+            self.check_empty(asset.ticker.clone(), "asset ticker".to_string())?;
+        }
 
-        if self.max_block_old <= 0 {
+        if self.oracle_policy.max_blocks_old == 0 {
             return Err(ContractError::MalformedInput {
-                input: "max_block_stale".to_string(),
+                input: "max_blocks_old".to_string(),
                 reason: "must be >=1".to_string(),
             });
         }
-        Self::validate_denom(&self.denom_ntrn)?;
-        Self::validate_denom(&self.denom_usd)?;
-        Ok(())
-    }
-    fn validate_denom(denom: &str) -> ContractResult<Response> {
-        let invalid_denom = |reason: &str| {
-            Err(ContractError::InvalidIbcDenom {
-                denom: String::from(denom),
-                reason: reason.to_string(),
-            })
-        };
-        // if it's an IBC denom
-        if denom.len() >= 4 && denom.starts_with("ibc/") {
-            // Step 1: Validate length
-            if denom.len() != 68 {
-                return invalid_denom("expected length of 68 chars");
+        if self.oracle_policy.max_seconds_old == 0 {
+            return Err(ContractError::MalformedInput {
+                input: "max_seconds_old".to_string(),
+                reason: "must be >=1".to_string(),
+            });
+        }
+        if let Some(dex_fallback) = &self.dex_fallback {
+            if dex_fallback.min_liquidity.is_zero() {
+                return Err(ContractError::MalformedInput {
+                    input: "min_liquidity".to_string(),
+                    reason: "must be >=1".to_string(),
+                });
             }
-
-            // Step 2: Validate prefix
-            if !denom.starts_with("ibc/") {
-                return invalid_denom("expected prefix 'ibc/'");
+        }
+        if let Some(max_dex_deviation_bps) = self.max_dex_deviation_bps {
+            if max_dex_deviation_bps == 0 || max_dex_deviation_bps > 10000 {
+                return Err(ContractError::MalformedInput {
+                    input: "max_dex_deviation_bps".to_string(),
+                    reason: "must be in range [1-10000]".to_string(),
+                });
             }
-
-            // Step 3: Validate hash
-            if !denom
-                .chars()
-                .skip(4)
-                // c.is_ascii_hexdigit() could have been used here, but it allows lowercase characters
-                .all(|c| matches!(c, '0'..='9' | 'A'..='F'))
+        }
+        if let Some(price_aggregation) = &self.price_aggregation {
+            if price_aggregation.quorum == 0
+                || price_aggregation.quorum > price_aggregation.feeds.len() as u64
+            {
+                return Err(ContractError::MalformedInput {
+                    input: "quorum".to_string(),
+                    reason: "must be >=1 and <= the number of feeds".to_string(),
+                });
+            }
+            for feed in price_aggregation.feeds.iter() {
+                match feed {
+                    PriceFeedConfig::Dex { min_liquidity } if min_liquidity.is_zero() => {
+                        return Err(ContractError::MalformedInput {
+                            input: "min_liquidity".to_string(),
+                            reason: "must be >=1".to_string(),
+                        });
+                    }
+                    PriceFeedConfig::Contract { address } => {
+                        self.check_empty(address.clone(), "address".to_string())?
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(depeg_guard) = &self.depeg_guard {
+            if depeg_guard.min_price.is_zero() || depeg_guard.min_price >= depeg_guard.max_price {
+                return Err(ContractError::MalformedInput {
+                    input: "depeg_guard".to_string(),
+                    reason: "min_price must be >0 and below max_price".to_string(),
+                });
+            }
+        }
+        validate_denom_format(&self.denom_ntrn)?;
+        validate_denom_format(&self.denom_usd)?;
+        for asset in self.assets.iter() {
+            validate_denom_format(&asset.denom)?;
+        }
+        if let Some(liquidity_check) = &self.liquidity_check {
+            if liquidity_check.min_fill_ratio.is_zero()
+                || liquidity_check.min_fill_ratio > Decimal::one()
+            {
+                return Err(ContractError::MalformedInput {
+                    input: "min_fill_ratio".to_string(),
+                    reason: "must be in range (0-1]".to_string(),
+                });
+            }
+        }
+        match &self.run_cap {
+            Some(RunCap::Absolute { amount }) if amount.is_zero() => {
+                return Err(ContractError::MalformedInput {
+                    input: "run_cap".to_string(),
+                    reason: "must be >=1".to_string(),
+                });
+            }
+            Some(RunCap::DepthFraction { fraction })
+                if fraction.is_zero() || *fraction > Decimal::one() =>
             {
-                return invalid_denom("invalid denom hash");
+                return Err(ContractError::MalformedInput {
+                    input: "run_cap".to_string(),
+                    reason: "must be in range (0-1]".to_string(),
+                });
             }
+            _ => {}
+        }
+        if self.max_consecutive_failures == Some(0) {
+            return Err(ContractError::MalformedInput {
+                input: "max_consecutive_failures".to_string(),
+                reason: "must be >=1".to_string(),
+            });
         }
-        Ok(Response::new())
+        let min_tradable_amounts = self.min_tradable_amounts.clone().unwrap_or_default();
+        for (i, coin) in min_tradable_amounts.iter().enumerate() {
+            if (coin.denom != self.denom_ntrn && coin.denom != self.denom_usd)
+                || min_tradable_amounts[..i]
+                    .iter()
+                    .any(|other| other.denom == coin.denom)
+            {
+                return Err(ContractError::MalformedInput {
+                    input: "min_tradable_amounts".to_string(),
+                    reason: "must have at most one amount of denom_ntrn and of denom_usd"
+                        .to_string(),
+                });
+            }
+        }
+        if let Some(ExecutionVenueConfig::Astroport { pair_address }) = &self.execution_venue {
+            self.check_empty(pair_address.clone(), "pair_address".to_string())?;
+        }
+        // the hop denoms are checked against the asset registry on instantiate
+        for venue in self.venues.iter().flatten() {
+            validate_route(&venue.hops, &self.denom_usd, &self.denom_ntrn)?;
+        }
+        Ok(())
     }
-
     pub fn check_empty(&self, input: String, kind: String) -> ContractResult<()> {
         if input.is_empty() {
             return Err(ContractError::EmptyValue { kind: kind });
@@ -92,11 +197,53 @@ pub enum ExecuteMsg {
     DepositDca {
         max_sell_amount: Uint128,
         max_slippage_basis_points: u128,
+        // defaults to ImmediateOrCancel
+        order_type: Option<ScheduleOrderType>,
+        // only valid for ImmediateOrCancel and FillOrKill
+        max_average_slippage_basis_points: Option<u128>,
+        // the denom to buy, defaults to denom_ntrn. Sell schedules always buy denom_usd
+        target_denom: Option<String>,
+        // defaults to Buy, Sell schedules deposit denom_ntrn
+        side: Option<ScheduleSide>,
+        // only valid for ImmediateOrCancel, e.g. [10, 25, 50]
+        slippage_ladder: Option<Vec<u128>>,
+        // only valid for ImmediateOrCancel, e.g. 40% at +0, 30% at +10 and 30% at +25 bps
+        tranche_ladder: Option<Vec<TrancheStep>>,
     },
     // withdraws any remaining funds form the DCA strategy
     WithdrawAll {},
+    // resumes a parked schedule of the sender and resets its failures
+    ResumeSchedule {
+        schedule_id: u128,
+    },
     // runs the registered user schedules
     RunSchedules {},
+    // runs only the sender's schedules, so owners can run them on their own cadence
+    RunMySchedules {},
+    // adds or replaces an asset in the registry, owner only
+    RegisterAsset {
+        asset: Asset,
+    },
+    // enables or disables a registered asset, owner only
+    SetAssetEnabled {
+        denom: String,
+        enabled: bool,
+    },
+    // adds a DEX pair used for route discovery, owner only
+    RegisterDexPair {
+        denom_a: String,
+        denom_b: String,
+    },
+    // removes a DEX pair used for route discovery, owner only
+    RemoveDexPair {
+        denom_a: String,
+        denom_b: String,
+    },
+    // sets the route from denom_usd to target_denom, removes it if hops is None, owner only
+    SetRoute {
+        target_denom: String,
+        hops: Option<Vec<String>>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -104,6 +251,64 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     GetFormated {},
     GetSchedules { address: Addr },
+    GetAssets {},
+    GetRoute { target_denom: String },
+}
+
+/// The query interface expected from oracle contracts used as a price feed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleContractQueryMsg {
+    Price { base: String, quote: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct OracleContractPriceResponse {
+    pub price: Decimal,
+}
+
+/// The swap message of Astroport pair contracts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AstroportExecuteMsg {
+    Swap {
+        offer_asset: AstroportAsset,
+        ask_asset_info: Option<AstroportAssetInfo>,
+        belief_price: Option<Decimal>,
+        max_spread: Option<Decimal>,
+        to: Option<String>,
+    },
+}
+
+/// The swap simulation query of Astroport pair contracts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AstroportQueryMsg {
+    Simulation {
+        offer_asset: AstroportAsset,
+        ask_asset_info: Option<AstroportAssetInfo>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AstroportSimulationResponse {
+    pub return_amount: Uint128,
+    pub spread_amount: Uint128,
+    pub commission_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AstroportAsset {
+    pub info: AstroportAssetInfo,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AstroportAssetInfo {
+    Token { contract_addr: String },
+    NativeToken { denom: String },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
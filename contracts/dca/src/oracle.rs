@@ -0,0 +1,150 @@
+use crate::error::{ContractError, ContractResult};
+use crate::msg::{OracleContractPriceResponse, OracleContractQueryMsg};
+use crate::state::{Config, PriceFeedConfig, PriceSource, CONFIG};
+use crate::utils::{get_dex_price, get_price};
+use cosmwasm_std::{Decimal, Deps, Env, Uint128};
+
+/// A source of the price of denom_ntrn in denom_usd.
+pub trait PriceFeed {
+    // the name the feed is reported under in attributes
+    fn name(&self) -> String;
+    // the current price of NTRN in USD, or an error if the feed cannot provide one
+    fn price(&self, deps: Deps, env: &Env, config: &Config) -> ContractResult<Decimal>;
+}
+
+/// Slinky x/oracle price, validated against the oracle policy.
+pub struct SlinkyFeed;
+
+impl PriceFeed for SlinkyFeed {
+    fn name(&self) -> String {
+        PriceSource::Slinky.as_str().to_string()
+    }
+
+    fn price(&self, deps: Deps, env: &Env, _config: &Config) -> ContractResult<Decimal> {
+        get_price(deps, env.clone())
+    }
+}
+
+/// Top of book price of the Neutron DEX pool of the pair.
+pub struct DexFeed {
+    pub min_liquidity: Uint128,
+}
+
+impl PriceFeed for DexFeed {
+    fn name(&self) -> String {
+        PriceSource::Dex.as_str().to_string()
+    }
+
+    fn price(&self, deps: Deps, _env: &Env, config: &Config) -> ContractResult<Decimal> {
+        get_dex_price(deps, config, self.min_liquidity)
+    }
+}
+
+/// Price served by another oracle contract implementing OracleContractQueryMsg.
+pub struct ContractFeed {
+    pub address: String,
+}
+
+impl PriceFeed for ContractFeed {
+    fn name(&self) -> String {
+        format!("contract:{}", self.address)
+    }
+
+    fn price(&self, deps: Deps, _env: &Env, config: &Config) -> ContractResult<Decimal> {
+        let pair = &config.pair_data.currency_pair;
+        let response: OracleContractPriceResponse = deps.querier.query_wasm_smart(
+            self.address.clone(),
+            &OracleContractQueryMsg::Price {
+                base: pair.base.clone(),
+                quote: pair.quote.clone(),
+            },
+        )?;
+        if response.price.is_zero() {
+            return Err(ContractError::InvalidPrice);
+        }
+        Ok(response.price)
+    }
+}
+
+pub fn build_price_feed(feed: &PriceFeedConfig) -> Box<dyn PriceFeed> {
+    match feed {
+        PriceFeedConfig::Slinky {} => Box::new(SlinkyFeed),
+        PriceFeedConfig::Dex { min_liquidity } => Box::new(DexFeed {
+            min_liquidity: *min_liquidity,
+        }),
+        PriceFeedConfig::Contract { address } => Box::new(ContractFeed {
+            address: address.clone(),
+        }),
+    }
+}
+
+// Get price of NTRN in USD for a run.
+// Uses the median of the configured feeds if aggregation is enabled, otherwise slinky
+// with the DEX pool as fallback when slinky is stale or nil
+pub fn get_run_price(deps: Deps, env: Env) -> ContractResult<(Decimal, PriceSource)> {
+    let config = CONFIG.load(deps.storage)?;
+    if let Some(price_aggregation) = &config.price_aggregation {
+        let feeds: Vec<Box<dyn PriceFeed>> = price_aggregation
+            .feeds
+            .iter()
+            .map(build_price_feed)
+            .collect();
+        let price = aggregate_median(deps, &env, &config, &feeds, price_aggregation.quorum)?;
+        return Ok((price, PriceSource::Median));
+    }
+
+    match SlinkyFeed.price(deps, &env, &config) {
+        Ok(price) => Ok((price, PriceSource::Slinky)),
+        Err(
+            err @ (ContractError::PriceTooOld { .. }
+            | ContractError::PriceTooOldSeconds { .. }
+            | ContractError::PriceIsNil { .. }),
+        ) => match &config.dex_fallback {
+            Some(dex_fallback) => {
+                let feed = DexFeed {
+                    min_liquidity: dex_fallback.min_liquidity,
+                };
+                Ok((feed.price(deps, &env, &config)?, PriceSource::Dex))
+            }
+            None => Err(err),
+        },
+        Err(err) => Err(err),
+    }
+}
+
+// Median of the prices returned by the feeds. Feeds that error are treated as missing,
+// and at least quorum feeds must return a price
+pub fn aggregate_median(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    feeds: &[Box<dyn PriceFeed>],
+    quorum: u64,
+) -> ContractResult<Decimal> {
+    let prices: Vec<Decimal> = feeds
+        .iter()
+        .filter_map(|feed| feed.price(deps, env, config).ok())
+        .collect();
+
+    if (prices.len() as u64) < quorum || prices.is_empty() {
+        return Err(ContractError::QuorumNotReached {
+            available: prices.len() as u64,
+            required: quorum,
+        });
+    }
+    median(prices)
+}
+
+pub fn median(mut prices: Vec<Decimal>) -> ContractResult<Decimal> {
+    if prices.is_empty() {
+        return Err(ContractError::InvalidPrice);
+    }
+    prices.sort();
+    let middle = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        return Ok(prices[middle]);
+    }
+    let sum = prices[middle - 1].checked_add(prices[middle])?;
+    sum.checked_div(Decimal::from_ratio(2u128, 1u128))
+        .map_err(|_| ContractError::DecimalDivisionError)
+}
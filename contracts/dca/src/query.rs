@@ -1,7 +1,7 @@
 use crate::error::ContractResult;
-use crate::state::SCHEDULES;
+use crate::state::{Asset, ASSETS, CONFIG, SCHEDULES};
 use crate::utils::*;
-use cosmwasm_std::{to_json_binary, Addr, Binary, Decimal, Deps, Env};
+use cosmwasm_std::{to_json_binary, Addr, Binary, Decimal, Deps, Env, Order, StdResult};
 
 pub fn query_recent_valid_prices_formatted(deps: Deps, env: Env) -> ContractResult<Binary> {
     let price: Decimal = get_price(deps, env)?;
@@ -20,3 +20,19 @@ pub fn get_schedules(deps: Deps, _env: Env, sender: &Addr) -> ContractResult<Bin
 
     Ok(to_json_binary(&user_schedules)?)
 }
+
+pub fn get_route(deps: Deps, _env: Env, target_denom: String) -> ContractResult<Binary> {
+    let config = CONFIG.load(deps.storage)?;
+    let route = find_route(deps.storage, &config, &target_denom)?;
+
+    Ok(to_json_binary(&route)?)
+}
+
+pub fn get_assets(deps: Deps, _env: Env) -> ContractResult<Binary> {
+    let assets = ASSETS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, asset)| asset))
+        .collect::<StdResult<Vec<Asset>>>()?;
+
+    Ok(to_json_binary(&assets)?)
+}
@@ -0,0 +1,55 @@
+use crate::error::{ContractError, ContractResult};
+use crate::state::{LadderOrder, PendingOrder, PendingTrancheOp, PendingVenueSwap};
+use cosmwasm_std::{from_json, to_json_binary, CosmosMsg, Reply, SubMsg};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The kind of operation a submessage performs and the context its reply needs. It is
+/// carried in the submessage payload and returned with the reply, so every kind of
+/// submessage can be dispatched without reserving ranges of reply ids.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyPayload {
+    // an order of a schedule placed on the execution venue
+    RunSchedule {
+        order: PendingOrder,
+        // the step of the schedule's slippage ladder the order was placed at
+        ladder: Option<LadderOrder>,
+    },
+    // a part of a schedule's run amount, or a multi-hop swap
+    VenueSwap {
+        swap: PendingVenueSwap,
+    },
+    // the batch order of a run, its parts are stored in PENDING_BATCH
+    Batch {},
+    // the withdraw of the filled part of a maker tranche
+    WithdrawFilled {
+        op: PendingTrancheOp,
+    },
+    // the cancel of the unfilled part of a maker tranche
+    CancelLimitOrder {
+        op: PendingTrancheOp,
+    },
+}
+
+impl ReplyPayload {
+    // the reply id of the kind, only informational as replies dispatch on the payload
+    pub fn reply_id(&self) -> u64 {
+        match self {
+            ReplyPayload::RunSchedule { .. } => 1,
+            ReplyPayload::VenueSwap { .. } => 2,
+            ReplyPayload::Batch {} => 3,
+            ReplyPayload::WithdrawFilled { .. } => 4,
+            ReplyPayload::CancelLimitOrder { .. } => 5,
+        }
+    }
+
+    // a submessage replied to on success and on error, carrying the payload
+    pub fn sub_msg(&self, msg: impl Into<CosmosMsg>) -> ContractResult<SubMsg> {
+        Ok(SubMsg::reply_always(msg, self.reply_id()).with_payload(to_json_binary(self)?))
+    }
+
+    pub fn from_reply(reply: &Reply) -> ContractResult<ReplyPayload> {
+        from_json(&reply.payload).map_err(|_| ContractError::UnknownReply { id: reply.id })
+    }
+}
@@ -1,6 +1,6 @@
 use cosmwasm_std::Addr;
-use cosmwasm_std::Uint128;
-use cw_storage_plus::Item;
+use cosmwasm_std::{Coin, Decimal, Uint128};
+use cw_storage_plus::{Item, Map};
 use neutron_std::types::slinky::types::v1::CurrencyPair;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -12,6 +12,59 @@ pub struct PairData {
     pub denom_usd: String,
     pub currency_pair: CurrencyPair,
     pub pair_id: String,
+    // how the price of currency_pair is read from slinky
+    pub price_path: PricePath,
+}
+
+/// This structure stores a registered asset and its slinky ticker.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Asset {
+    // the chain denom of the asset, e.g. untrn
+    pub denom: String,
+    // the slinky ticker the asset is priced under, e.g. NTRN
+    pub ticker: String,
+    // the display decimals of the asset
+    pub decimals: u32,
+    // disabled assets cannot be priced
+    pub enabled: bool,
+    // the expected origin of an ibc/ denom, required for IBC denoms
+    pub ibc_trace: Option<IbcTrace>,
+}
+
+/// The transfer path and base denom an IBC denom must resolve to.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct IbcTrace {
+    // e.g. transfer/channel-1
+    pub path: String,
+    // e.g. uusdc
+    pub base_denom: String,
+}
+
+/// How the price of a currency pair is derived from x/oracle.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PricePath {
+    // the pair is quoted directly by x/oracle
+    Direct {
+        pair: CurrencyPair,
+    },
+    // the pair is derived from both legs' USD prices as base/USD ÷ quote/USD
+    Cross {
+        base: CurrencyPair,
+        quote: CurrencyPair,
+    },
+}
+
+impl PricePath {
+    // every x/oracle pair the path reads a price from
+    pub fn legs(&self) -> Vec<&CurrencyPair> {
+        match self {
+            PricePath::Direct { pair } => vec![pair],
+            PricePath::Cross { base, quote } => vec![base, quote],
+        }
+    }
 }
 
 /// This structure stores the concentrated pair parameters.
@@ -28,6 +81,185 @@ pub struct Schedule {
     pub max_slippage_basis_points: u128,
     // the unique id of the schedule
     pub id: u128,
+    // how the schedule's orders are placed
+    pub order_type: ScheduleOrderType,
+    // bound on the average fill price of taker orders, in basis points above the oracle price
+    pub max_average_slippage_basis_points: Option<u128>,
+    // maker tranches placed by the schedule that have not been withdrawn yet
+    pub open_tranches: Vec<OpenTranche>,
+    // the denom bought by the schedule, anything but denom_ntrn is bought by multi-hop swap
+    pub target_denom: String,
+    // whether the schedule buys with denom_usd or sells denom_ntrn
+    pub side: ScheduleSide,
+    // increasing slippages in basis points an ImmediateOrCancel order is retried at when it
+    // fills nothing, up to max_slippage_basis_points. Empty places one order at the max
+    pub slippage_ladder: Vec<u128>,
+    // splits each run of an ImmediateOrCancel schedule into one order per step at
+    // increasing limit prices. Empty places the whole run amount at one price
+    pub tranche_ladder: Vec<TrancheStep>,
+    // the runs in a row the schedule's order failed or filled nothing
+    pub consecutive_failures: u64,
+    // parked schedules are not run until their owner resumes them
+    pub parked: bool,
+}
+
+/// One order of a schedule's tranche ladder.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TrancheStep {
+    // the fraction of the run amount placed at this step, the steps add up to 1
+    pub fraction: Decimal,
+    // the limit price of the step in basis points above the oracle price
+    pub slippage_basis_points: u128,
+}
+
+/// The direction a schedule trades the pair in.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleSide {
+    // deposits denom_usd and buys the target denom
+    Buy,
+    // deposits denom_ntrn and sells it for denom_usd
+    Sell,
+}
+
+/// How a schedule's orders are placed on the DEX.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleOrderType {
+    // taker orders that fill what they can at the limit price and cancel the rest
+    ImmediateOrCancel,
+    // taker orders that either fill the whole run amount or nothing
+    FillOrKill,
+    // maker orders resting at the oracle price for expiry_seconds
+    GoodTilTime { expiry_seconds: u64 },
+    // maker orders at the oracle price that only rest until the end of the block
+    JustInTime,
+}
+
+impl ScheduleOrderType {
+    // whether the unfilled part of the order rests on the book as a tranche
+    pub fn is_maker(&self) -> bool {
+        matches!(
+            self,
+            ScheduleOrderType::GoodTilTime { .. } | ScheduleOrderType::JustInTime
+        )
+    }
+}
+
+/// This structure stores a maker tranche resting on the DEX.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OpenTranche {
+    // the DEX tranche key returned when the order was placed
+    pub tranche_key: String,
+    // the amount of denom_usd resting in the tranche when it was placed
+    pub amount: Uint128,
+    // the unix time in seconds the tranche expires at
+    pub expiration: u64,
+    // set once the filled part is withdrawn, a cancel failing after it means nothing was
+    // left resting in the tranche
+    pub withdrawn: bool,
+}
+
+/// A withdraw or cancel of a maker tranche waiting for its reply.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PendingTrancheOp {
+    pub schedule_id: u128,
+    // receives the proceeds if the schedule no longer exists
+    pub owner: Addr,
+    pub tranche_key: String,
+}
+
+/// An order of a schedule placed on the execution venue waiting for its reply. It carries
+/// what is needed to route the proceeds if the schedule is closed while it is in flight.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PendingOrder {
+    pub schedule_id: u128,
+    pub owner: Addr,
+    // the amount the order was placed with
+    pub amount_in: Uint128,
+    // the denom the order buys
+    pub token_out: String,
+    // maker orders are received by the contract and may rest in a tranche
+    pub maker: bool,
+}
+
+/// One part of a schedule's run amount sent to a venue or placed at a step of its tranche
+/// ladder, or a multi-hop swap of another target denom, waiting for its reply.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PendingVenueSwap {
+    pub schedule_id: u128,
+    // the amount of denom_usd sent to the venue
+    pub amount_in: Uint128,
+    // multi-hop swaps fill all or nothing, direct orders report their taker fill
+    pub multi_hop: bool,
+    // the last part of the schedule in this run, its reply reports the aggregate fill
+    pub last_part: bool,
+}
+
+/// An order of a schedule's slippage ladder waiting for its reply.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LadderOrder {
+    // the index of the ladder step the order was placed at
+    pub step: usize,
+    pub amount_in: Uint128,
+    // the run price in whole denom_usd per denom_ntrn, retries are placed against it
+    pub price: Decimal,
+}
+
+/// Simulates taker orders before placing them to skip or downsize thin books.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LiquidityCheck {
+    // the min fraction of the sell amount the simulation must fill, in (0, 1]
+    pub min_fill_ratio: Decimal,
+    // place the simulated fill instead of skipping when it is below min_fill_ratio
+    pub downsize: bool,
+}
+
+/// The max amount of denom_usd sold by all schedules together in one block.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunCap {
+    // a fixed amount of denom_usd
+    Absolute { amount: Uint128 },
+    // a fraction of the denom_usd it takes to buy all denom_ntrn in the DEX pool, in (0, 1]
+    DepthFraction { fraction: Decimal },
+}
+
+/// The exchange the orders of the pair are executed on.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionVenueConfig {
+    // limit orders on the Neutron DEX
+    NeutronDex {},
+    // swaps against an Astroport pair contract, taker orders only
+    Astroport { pair_address: String },
+}
+
+/// A schedule's share of the batch order of a run.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct BatchPart {
+    pub schedule_id: u128,
+    // receives the schedule's share of the batch proceeds
+    pub owner: Addr,
+    // the amount of denom_usd the schedule put into the batch
+    pub amount_in: Uint128,
+}
+
+/// A pool route from denom_usd to denom_ntrn that runs can split orders across.
+/// A route of two denoms is the pair's own pool, longer routes go through other
+/// USD denoms such as a second USDC. All fee tiers of a pool are swept by the same order.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Venue {
+    pub hops: Vec<String>,
 }
 /// This structure stores the concentrated pair parameters.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -39,13 +271,112 @@ pub struct Schedules {
     pub nonce: u128,
 }
 
+/// This structure stores the freshness requirements for oracle prices.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OraclePolicy {
+    // the max blocks old for the oracle price
+    pub max_blocks_old: u64,
+    // the max seconds old for the oracle price
+    pub max_seconds_old: u64,
+    // whether the x/marketmap ticker must be enabled
+    pub require_enabled: bool,
+    // the min number of providers the x/marketmap market must be configured with
+    pub min_provider_count: u64,
+}
+
+/// This structure stores the parameters of the DEX fallback price source.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct DexFallback {
+    // the min amount of denom_ntrn resting in the pool for its price to be used
+    pub min_liquidity: Uint128,
+}
+
+/// The source a run's price was taken from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    Slinky,
+    Dex,
+    Median,
+}
+
+impl PriceSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriceSource::Slinky => "slinky",
+            PriceSource::Dex => "dex",
+            PriceSource::Median => "median",
+        }
+    }
+}
+
+/// A price feed taking part in the median price aggregation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceFeedConfig {
+    // the slinky price, subject to the oracle policy
+    Slinky {},
+    // the top of book price of the DEX pool
+    Dex { min_liquidity: Uint128 },
+    // the price served by another oracle contract
+    Contract { address: String },
+}
+
+/// This structure stores the median price aggregation parameters.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PriceAggregation {
+    // the feeds the median is taken over
+    pub feeds: Vec<PriceFeedConfig>,
+    // the min number of feeds that must return a price
+    pub quorum: u64,
+}
+
+/// This structure stores the depeg protection of denom_usd, runs are paused while it trades
+/// outside its band. The stablecoin is priced through its registered ticker against USD,
+/// e.g. USDC/USD. The pair itself is always quoted in that ticker, so orders placed within
+/// the band need no adjustment
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct DepegGuard {
+    // the lowest USD price denom_usd is considered pegged at
+    pub min_price: Decimal,
+    // the highest USD price denom_usd is considered pegged at
+    pub max_price: Decimal,
+}
+
 /// This structure stores the concentrated pair parameters.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Config {
     pub pair_data: PairData,
-    // the max blocks old for the oracle price
-    pub max_blocks_old: u64,
+    // the freshness policy applied to every oracle price
+    pub oracle_policy: OraclePolicy,
+    // the DEX pool price used when slinky is stale or nil, disabled if None
+    pub dex_fallback: Option<DexFallback>,
+    // the max deviation in basis points between the DEX pool and oracle price, disabled if None
+    pub max_dex_deviation_bps: Option<u64>,
+    // the median aggregation used instead of slinky, disabled if None
+    pub price_aggregation: Option<PriceAggregation>,
+    // the depeg protection of denom_usd, disabled if None
+    pub depeg_guard: Option<DepegGuard>,
+    // the venues ImmediateOrCancel runs are split across by depth, a single order if empty
+    pub venues: Vec<Venue>,
+    // the exchange orders on the pair's own pool are sent to
+    pub execution_venue: ExecutionVenueConfig,
+    // the pre-check of taker orders against a simulation, disabled if None
+    pub liquidity_check: Option<LiquidityCheck>,
+    // the cap on the total sold per block, uncapped if None
+    pub run_cap: Option<RunCap>,
+    // whether ImmediateOrCancel schedules are placed together as one order per run
+    pub batch_mode: bool,
+    // schedules whose orders fail this many runs in a row are parked, never if None
+    pub max_consecutive_failures: Option<u64>,
+    // schedules left with less than the amount of the denom they sell are closed and
+    // refunded as the DEX cannot fill them. Denoms without an amount are never closed
+    pub min_tradable_amounts: Vec<Coin>,
     // the owner of the contract
     pub owner: Addr,
     // the max number of schedules
@@ -55,3 +386,17 @@ pub struct Config {
 // pub const PAIRDATA: Item<PairData> = Item::new("data");
 pub const CONFIG: Item<Config> = Item::new("data");
 pub const SCHEDULES: Item<Schedules> = Item::new("user_schedules");
+// registered assets keyed by denom
+pub const ASSETS: Map<&str, Asset> = Map::new("assets");
+// DEX pairs known to have liquidity, keyed by pair id, used for route discovery
+pub const DEX_PAIRS: Map<&str, (String, String)> = Map::new("dex_pairs");
+// owner configured multi-hop routes keyed by target denom, starting at denom_usd
+pub const ROUTES: Map<&str, Vec<String>> = Map::new("routes");
+// the schedules in the batch order waiting for its reply
+pub const PENDING_BATCH: Item<Vec<BatchPart>> = Item::new("pending_batch");
+// the denom_ntrn of past batches that did not divide evenly, paid out with the next batch
+pub const BATCH_DUST: Item<Uint128> = Item::new("batch_dust");
+// the amounts in and out of a schedule's venue swaps so far in the current run
+pub const VENUE_FILLS: Map<u128, (Uint128, Uint128)> = Map::new("venue_fills");
+// the height of the last capped run and how much of the run cap the runs of that block used
+pub const RUN_CAP_USED: Item<(u64, Uint128)> = Item::new("run_cap_used");
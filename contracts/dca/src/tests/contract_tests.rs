@@ -0,0 +1,1840 @@
+use crate::contract::{execute, instantiate, reply};
+use crate::error::ContractError;
+use crate::execute::to_dex_price;
+use crate::msg::{ExecuteMsg, InstantiateMsg};
+use crate::reply::ReplyPayload;
+use crate::state::{
+    Asset, DepegGuard, IbcTrace, LiquidityCheck, OpenTranche, OraclePolicy, PendingVenueSwap,
+    RunCap, Schedule, ScheduleOrderType, TrancheStep, Venue, ASSETS, BATCH_DUST, CONFIG, DEX_PAIRS,
+    PENDING_BATCH, ROUTES, SCHEDULES, VENUE_FILLS,
+};
+use crate::utils::{
+    apply_slippage, cross_price, get_pair_id_str, load_enabled_asset, pair_from_registry,
+};
+use cosmwasm_std::testing::{message_info, mock_env, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, AnyMsg, BankMsg, Binary, Coin,
+    ContractResult as CwContractResult, CosmosMsg, Decimal, Empty, GrpcQuery, MsgResponse,
+    OwnedDeps, Querier, QuerierResult, QueryRequest, Reply, Response, SubMsg, SubMsgResponse,
+    SubMsgResult, SystemError, SystemResult, Uint128,
+};
+use neutron_std::shim::Timestamp;
+use neutron_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use neutron_std::types::ibc::applications::transfer::v1::{
+    DenomTrace, QueryDenomTraceRequest, QueryDenomTraceResponse,
+};
+use neutron_std::types::neutron::dex::{
+    LimitOrderType, MsgCancelLimitOrder, MsgCancelLimitOrderResponse, MsgMultiHopSwap,
+    MsgPlaceLimitOrder, MsgPlaceLimitOrderResponse, MsgWithdrawFilledLimitOrder,
+    MsgWithdrawFilledLimitOrderResponse, QuerySimulatePlaceLimitOrderRequest,
+    QuerySimulatePlaceLimitOrderResponse,
+};
+use neutron_std::types::slinky::{
+    marketmap::v1::{
+        Market, MarketMap, MarketMapResponse, MarketRequest, MarketResponse, ProviderConfig, Ticker,
+    },
+    oracle::v1::{GetAllCurrencyPairsResponse, GetPriceRequest, GetPriceResponse, QuotePrice},
+    types::v1::CurrencyPair,
+};
+use prost::Message;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use test_case::test_case;
+
+/// Querier answering the slinky queries from a set of USD prices and the IBC denom trace
+/// queries from a set of traces, every other query is answered by the wrapped MockQuerier.
+/// Every market is enabled with one provider and priced in the current block.
+pub struct StargateQuerier {
+    pub base: MockQuerier,
+    // prices keyed by BASE/QUOTE
+    pub prices: BTreeMap<String, Decimal>,
+    // IBC denom traces keyed by hash
+    pub traces: BTreeMap<String, DenomTrace>,
+    // the most a simulated limit order fills as taker, simulations fail if None
+    pub simulated_fill: Option<Uint128>,
+}
+
+impl StargateQuerier {
+    pub fn set_price(&mut self, pair: &str, price: &str) {
+        self.prices
+            .insert(pair.to_string(), Decimal::from_str(price).unwrap());
+    }
+
+    fn currency_pairs(&self) -> Vec<CurrencyPair> {
+        self.prices
+            .keys()
+            .map(|key| {
+                let (base, quote) = key.split_once('/').unwrap();
+                CurrencyPair {
+                    base: base.to_string(),
+                    quote: quote.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    fn market(&self, pair: &CurrencyPair) -> Market {
+        Market {
+            ticker: Some(Ticker {
+                currency_pair: Some(pair.clone()),
+                enabled: true,
+                ..Default::default()
+            }),
+            provider_configs: vec![ProviderConfig::default()],
+        }
+    }
+
+    // the response to a slinky query, None for queries it does not answer
+    fn slinky_query(&self, path: &str, data: &Binary, grpc: bool) -> Option<Binary> {
+        let env = mock_env();
+        match path {
+            "/slinky.oracle.v1.Query/GetPrice" => {
+                let pair = GetPriceRequest::decode(data.as_slice())
+                    .ok()?
+                    .currency_pair?;
+                let price = self.prices.get(&format!("{}/{}", pair.base, pair.quote))?;
+                Some(encode(
+                    &GetPriceResponse {
+                        price: Some(QuotePrice {
+                            price: Uint128::new(1_000_000).mul_floor(*price).to_string(),
+                            block_timestamp: Some(Timestamp {
+                                seconds: env.block.time.seconds() as i64,
+                                nanos: 0,
+                            }),
+                            block_height: env.block.height,
+                        }),
+                        nonce: 1,
+                        decimals: 6,
+                        ..Default::default()
+                    },
+                    grpc,
+                ))
+            }
+            "/slinky.oracle.v1.Query/GetAllCurrencyPairs" => Some(encode(
+                &GetAllCurrencyPairsResponse {
+                    currency_pairs: self.currency_pairs(),
+                },
+                grpc,
+            )),
+            "/slinky.marketmap.v1.Query/Market" => {
+                let pair = MarketRequest::decode(data.as_slice()).ok()?.currency_pair?;
+                let market = self
+                    .prices
+                    .contains_key(&format!("{}/{}", pair.base, pair.quote))
+                    .then(|| self.market(&pair));
+                Some(encode(&MarketResponse { market }, grpc))
+            }
+            "/slinky.marketmap.v1.Query/MarketMap" => Some(encode(
+                &MarketMapResponse {
+                    market_map: Some(MarketMap {
+                        markets: self
+                            .currency_pairs()
+                            .iter()
+                            .map(|pair| {
+                                (format!("{}/{}", pair.base, pair.quote), self.market(pair))
+                            })
+                            .collect(),
+                    }),
+                    ..Default::default()
+                },
+                grpc,
+            )),
+            "/neutron.dex.Query/SimulatePlaceLimitOrder" => {
+                let order = QuerySimulatePlaceLimitOrderRequest::decode(data.as_slice())
+                    .ok()?
+                    .msg?;
+                let amount_in = Uint128::from_str(&order.amount_in).ok()?;
+                let fill = amount_in.min(self.simulated_fill?);
+                Some(encode(
+                    &QuerySimulatePlaceLimitOrderResponse {
+                        resp: Some(MsgPlaceLimitOrderResponse {
+                            tranche_key: String::new(),
+                            coin_in: proto_coin(&order.token_in, amount_in.u128()),
+                            taker_coin_in: proto_coin(&order.token_in, fill.u128()),
+                            taker_coin_out: None,
+                        }),
+                    },
+                    grpc,
+                ))
+            }
+            "/ibc.applications.transfer.v1.Query/DenomTrace" => {
+                let hash = QueryDenomTraceRequest::decode(data.as_slice()).ok()?.hash;
+                Some(encode(
+                    &QueryDenomTraceResponse {
+                        denom_trace: self.traces.get(&hash).cloned(),
+                    },
+                    grpc,
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+// stargate queries are answered in JSON, gRPC queries in protobuf
+fn encode<T: Serialize + Message>(response: &T, grpc: bool) -> Binary {
+    match grpc {
+        true => Binary::from(response.encode_to_vec()),
+        false => to_json_binary(response).unwrap(),
+    }
+}
+
+impl Querier for StargateQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_json(bin_request) {
+            Ok(request) => request,
+            Err(err) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: err.to_string(),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        #[allow(deprecated)]
+        let (path, data, grpc) = match &request {
+            QueryRequest::Stargate { path, data } => (path, data, false),
+            QueryRequest::Grpc(GrpcQuery { path, data }) => (path, data, true),
+            _ => return self.base.raw_query(bin_request),
+        };
+        match self.slinky_query(path, data, grpc) {
+            Some(response) => SystemResult::Ok(CwContractResult::Ok(response)),
+            None => SystemResult::Err(SystemError::UnsupportedRequest { kind: path.clone() }),
+        }
+    }
+}
+
+pub type MockDeps = OwnedDeps<MockStorage, MockApi, StargateQuerier>;
+
+pub fn addr(name: &str) -> Addr {
+    MockApi::default().addr_make(name)
+}
+
+pub fn asset(denom: &str, ticker: &str, enabled: bool) -> Asset {
+    Asset {
+        denom: denom.to_string(),
+        ticker: ticker.to_string(),
+        decimals: 6,
+        enabled,
+        ibc_trace: None,
+    }
+}
+
+// NTRN at 0.5 USD and USDC at its peg, NTRN/USDC is priced across both
+pub fn mock_deps() -> MockDeps {
+    let mut querier = StargateQuerier {
+        base: MockQuerier::default(),
+        prices: BTreeMap::new(),
+        traces: BTreeMap::new(),
+        simulated_fill: None,
+    };
+    querier.set_price("NTRN/USD", "0.5");
+    querier.set_price("USDC/USD", "1");
+    querier.set_price("ATOM/USD", "5");
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier,
+        custom_query_type: PhantomData,
+    }
+}
+
+pub fn instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        owner: addr("owner").to_string(),
+        denom_ntrn: "untrn".to_string(),
+        denom_usd: "uusdc".to_string(),
+        assets: vec![asset("untrn", "NTRN", true), asset("uusdc", "USDC", true)],
+        oracle_policy: OraclePolicy {
+            max_blocks_old: 10,
+            max_seconds_old: 60,
+            require_enabled: true,
+            min_provider_count: 1,
+        },
+        dex_fallback: None,
+        max_dex_deviation_bps: None,
+        price_aggregation: None,
+        depeg_guard: None,
+        venues: None,
+        execution_venue: None,
+        liquidity_check: None,
+        run_cap: None,
+        batch_mode: None,
+        max_consecutive_failures: None,
+        min_tradable_amounts: None,
+        max_schedules: 10,
+    }
+}
+
+pub fn setup(msg: InstantiateMsg) -> MockDeps {
+    let mut deps = mock_deps();
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        message_info(&addr("owner"), &[]),
+        msg,
+    )
+    .unwrap();
+    deps
+}
+
+pub fn execute_as(
+    deps: &mut MockDeps,
+    sender: &str,
+    funds: &[Coin],
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        message_info(&addr(sender), funds),
+        msg,
+    )
+}
+
+pub fn deposit(
+    deps: &mut MockDeps,
+    sender: &str,
+    amount: u128,
+    order_type: ScheduleOrderType,
+) -> Result<Response, ContractError> {
+    execute_as(
+        deps,
+        sender,
+        &[Coin::new(amount, "uusdc")],
+        ExecuteMsg::DepositDca {
+            max_sell_amount: Uint128::new(amount),
+            max_slippage_basis_points: 100,
+            order_type: Some(order_type),
+            max_average_slippage_basis_points: None,
+            target_denom: None,
+            side: None,
+            slippage_ladder: None,
+            tranche_ladder: None,
+        },
+    )
+}
+
+pub fn run(deps: &mut MockDeps) -> Response {
+    execute_as(deps, "keeper", &[], ExecuteMsg::RunSchedules {}).unwrap()
+}
+
+pub fn schedule(deps: &MockDeps, id: u128) -> Option<Schedule> {
+    SCHEDULES
+        .load(&deps.storage)
+        .unwrap()
+        .schedules
+        .into_iter()
+        .find(|schedule| schedule.id == id)
+}
+
+// the submessages of a response that reply with the given payload kind
+pub fn sub_msgs(response: &Response, reply_id: u64) -> Vec<SubMsg> {
+    response
+        .messages
+        .iter()
+        .filter(|sub_msg| sub_msg.id == reply_id)
+        .cloned()
+        .collect()
+}
+
+#[allow(deprecated)]
+pub fn decode_msg<T: Message + Default>(msg: &CosmosMsg) -> T {
+    match msg {
+        CosmosMsg::Stargate { value, .. } => T::decode(value.as_slice()).unwrap(),
+        CosmosMsg::Any(AnyMsg { value, .. }) => T::decode(value.as_slice()).unwrap(),
+        msg => panic!("expected a protobuf message, got {:?}", msg),
+    }
+}
+
+#[allow(deprecated)]
+pub fn ok_result<T: Message>(type_url: &str, response: T) -> SubMsgResult {
+    SubMsgResult::Ok(SubMsgResponse {
+        events: vec![],
+        data: None,
+        msg_responses: vec![MsgResponse {
+            type_url: type_url.to_string(),
+            value: Binary::from(response.encode_to_vec()),
+        }],
+    })
+}
+
+pub fn reply_to(
+    deps: &mut MockDeps,
+    sub_msg: &SubMsg,
+    result: SubMsgResult,
+) -> Result<Response, ContractError> {
+    reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: sub_msg.id,
+            payload: sub_msg.payload.clone(),
+            gas_used: 0,
+            result,
+        },
+    )
+}
+
+pub fn proto_coin(denom: &str, amount: u128) -> Option<ProtoCoin> {
+    Some(ProtoCoin {
+        denom: denom.to_string(),
+        amount: amount.to_string(),
+    })
+}
+
+// the coins sent to an address by the bank messages of a response
+pub fn sent_to(response: &Response, to: &str) -> Vec<Coin> {
+    response
+        .messages
+        .iter()
+        .filter_map(|sub_msg| match &sub_msg.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount })
+                if *to_address == addr(to).to_string() =>
+            {
+                Some(amount.clone())
+            }
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+#[test]
+fn test_instantiate_prices_pair_through_registry() {
+    let deps = setup(instantiate_msg());
+    let pair = CONFIG.load(&deps.storage).unwrap().pair_data.currency_pair;
+    assert_eq!(
+        pair,
+        CurrencyPair {
+            base: "NTRN".to_string(),
+            quote: "USDC".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_register_asset() {
+    let mut deps = setup(instantiate_msg());
+    execute_as(
+        &mut deps,
+        "owner",
+        &[],
+        ExecuteMsg::RegisterAsset {
+            asset: asset("uatom", "ATOM", true),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        ASSETS.load(&deps.storage, "uatom").unwrap(),
+        asset("uatom", "ATOM", true)
+    );
+}
+
+#[test]
+fn test_register_asset_unauthorized() {
+    let mut deps = setup(instantiate_msg());
+    let result = execute_as(
+        &mut deps,
+        "user",
+        &[],
+        ExecuteMsg::RegisterAsset {
+            asset: asset("uatom", "ATOM", true),
+        },
+    );
+    assert_eq!(result, Err(ContractError::Unauthorized));
+    assert!(ASSETS.may_load(&deps.storage, "uatom").unwrap().is_none());
+}
+
+#[test]
+fn test_register_asset_without_market() {
+    let mut deps = setup(instantiate_msg());
+    let result = execute_as(
+        &mut deps,
+        "owner",
+        &[],
+        ExecuteMsg::RegisterAsset {
+            asset: asset("uosmo", "OSMO", true),
+        },
+    );
+    assert!(matches!(
+        result,
+        Err(ContractError::UnsupportedMarket { .. })
+    ));
+}
+
+#[test]
+fn test_set_asset_enabled() {
+    let mut deps = setup(instantiate_msg());
+    execute_as(
+        &mut deps,
+        "owner",
+        &[],
+        ExecuteMsg::RegisterAsset {
+            asset: asset("uatom", "ATOM", true),
+        },
+    )
+    .unwrap();
+
+    let set_enabled = |deps: &mut MockDeps, enabled: bool| {
+        execute_as(
+            deps,
+            "owner",
+            &[],
+            ExecuteMsg::SetAssetEnabled {
+                denom: "uatom".to_string(),
+                enabled,
+            },
+        )
+    };
+    set_enabled(&mut deps, false).unwrap();
+    assert_eq!(
+        load_enabled_asset(&deps.storage, "uatom"),
+        Err(ContractError::AssetDisabled {
+            denom: "uatom".to_string()
+        })
+    );
+    set_enabled(&mut deps, true).unwrap();
+    assert_eq!(
+        load_enabled_asset(&deps.storage, "uatom"),
+        Ok(asset("uatom", "ATOM", true))
+    );
+}
+
+#[test]
+fn test_set_asset_enabled_unregistered() {
+    let mut deps = setup(instantiate_msg());
+    let result = execute_as(
+        &mut deps,
+        "owner",
+        &[],
+        ExecuteMsg::SetAssetEnabled {
+            denom: "uosmo".to_string(),
+            enabled: true,
+        },
+    );
+    assert_eq!(
+        result,
+        Err(ContractError::AssetNotRegistered {
+            denom: "uosmo".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_pair_from_registry() {
+    let mut deps = setup(instantiate_msg());
+    ASSETS
+        .save(&mut deps.storage, "uatom", &asset("uatom", "ATOM", false))
+        .unwrap();
+    ASSETS
+        .save(&mut deps.storage, "ausdc", &asset("ausdc", "USDC", true))
+        .unwrap();
+
+    assert_eq!(
+        pair_from_registry(&deps.storage, "untrn", "uusdc"),
+        Ok(CurrencyPair {
+            base: "NTRN".to_string(),
+            quote: "USDC".to_string(),
+        })
+    );
+    assert_eq!(
+        pair_from_registry(&deps.storage, "uatom", "uusdc"),
+        Err(ContractError::AssetDisabled {
+            denom: "uatom".to_string()
+        })
+    );
+    assert_eq!(
+        pair_from_registry(&deps.storage, "uosmo", "uusdc"),
+        Err(ContractError::AssetNotRegistered {
+            denom: "uosmo".to_string()
+        })
+    );
+    assert!(matches!(
+        pair_from_registry(&deps.storage, "ausdc", "uusdc"),
+        Err(ContractError::InvalidCurrencyPair { .. })
+    ));
+}
+
+pub fn attribute(response: &Response, key: &str) -> Option<String> {
+    response
+        .attributes
+        .iter()
+        .find(|attr| attr.key == key)
+        .map(|attr| attr.value.clone())
+}
+
+fn depeg_deps(stable_price: &str) -> MockDeps {
+    let mut deps = setup(InstantiateMsg {
+        depeg_guard: Some(DepegGuard {
+            min_price: Decimal::from_str("0.98").unwrap(),
+            max_price: Decimal::from_str("1.02").unwrap(),
+        }),
+        ..instantiate_msg()
+    });
+    deps.querier.set_price("USDC/USD", stable_price);
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    deps
+}
+
+#[test]
+fn test_depeg_pause() {
+    let mut deps = depeg_deps("0.9");
+    let response = run(&mut deps);
+    assert_eq!(
+        attribute(&response, "action"),
+        Some("run_schedules_paused".to_string())
+    );
+    assert_eq!(
+        attribute(&response, "stable_price"),
+        Some("0.9".to_string())
+    );
+    assert!(sub_msgs(&response, 1).is_empty());
+}
+
+// NTRN/USDC is already the NTRN/USD price over the USDC/USD price, the order is placed at
+// 0.5 / 0.99 without dividing by the stable price again
+#[test]
+fn test_depeg_within_band() {
+    let mut deps = depeg_deps("0.99");
+    let response = run(&mut deps);
+    let expected = cross_price(
+        Decimal::from_str("0.5").unwrap(),
+        Decimal::from_str("0.99").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(attribute(&response, "price"), Some(expected.to_string()));
+    assert_eq!(
+        attribute(&response, "stable_price"),
+        Some("0.99".to_string())
+    );
+    assert_eq!(
+        limit_price(&sub_msgs(&response, 1)[0]),
+        sell_price(apply_slippage(expected, 100).unwrap())
+    );
+}
+
+const IBC_ATOM: &str = "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+
+// uatom registered as an IBC asset from channel-1, and the chain's trace of its denom
+fn ibc_deps(trace_path: &str) -> MockDeps {
+    let mut deps = setup(instantiate_msg());
+    ASSETS
+        .save(
+            &mut deps.storage,
+            IBC_ATOM,
+            &Asset {
+                ibc_trace: Some(IbcTrace {
+                    path: "transfer/channel-1".to_string(),
+                    base_denom: "uatom".to_string(),
+                }),
+                ..asset(IBC_ATOM, "ATOM", true)
+            },
+        )
+        .unwrap();
+    deps.querier.traces.insert(
+        IBC_ATOM.strip_prefix("ibc/").unwrap().to_string(),
+        DenomTrace {
+            path: trace_path.to_string(),
+            base_denom: "uatom".to_string(),
+        },
+    );
+    deps
+}
+
+fn register_dex_pair(deps: &mut MockDeps, denom_a: &str) -> Result<Response, ContractError> {
+    execute_as(
+        deps,
+        "owner",
+        &[],
+        ExecuteMsg::RegisterDexPair {
+            denom_a: denom_a.to_string(),
+            denom_b: "uusdc".to_string(),
+        },
+    )
+}
+
+#[test]
+fn test_register_dex_pair_checks_ibc_trace() {
+    let mut deps = ibc_deps("transfer/channel-1");
+    register_dex_pair(&mut deps, IBC_ATOM).unwrap();
+    assert!(DEX_PAIRS
+        .may_load(&deps.storage, &get_pair_id_str(IBC_ATOM, "uusdc"))
+        .unwrap()
+        .is_some());
+
+    let mut deps = ibc_deps("transfer/channel-9");
+    assert!(matches!(
+        register_dex_pair(&mut deps, IBC_ATOM),
+        Err(ContractError::IbcTraceMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_register_dex_pair_rejects_unregistered_ibc_denom() {
+    let mut deps = setup(instantiate_msg());
+    assert!(matches!(
+        register_dex_pair(&mut deps, IBC_ATOM),
+        Err(ContractError::InvalidIbcDenom { .. })
+    ));
+}
+
+#[test_case("transfer/channel-1" => true; "matching trace")]
+#[test_case("transfer/channel-9" => false; "mismatched trace")]
+fn test_set_route_checks_ibc_trace(trace_path: &str) -> bool {
+    let mut deps = ibc_deps(trace_path);
+    let hops = vec![
+        "uusdc".to_string(),
+        IBC_ATOM.to_string(),
+        "uosmo".to_string(),
+    ];
+    let result = execute_as(
+        &mut deps,
+        "owner",
+        &[],
+        ExecuteMsg::SetRoute {
+            target_denom: "uosmo".to_string(),
+            hops: Some(hops),
+        },
+    );
+    assert_eq!(
+        result.is_ok(),
+        ROUTES.may_load(&deps.storage, "uosmo").unwrap().is_some()
+    );
+    result.is_ok()
+}
+
+#[test]
+fn test_instantiate_checks_venue_hop_traces() {
+    let mut deps = mock_deps();
+    let result = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        message_info(&addr("owner"), &[]),
+        InstantiateMsg {
+            venues: Some(vec![Venue {
+                hops: vec![
+                    "uusdc".to_string(),
+                    IBC_ATOM.to_string(),
+                    "untrn".to_string(),
+                ],
+            }]),
+            ..instantiate_msg()
+        },
+    );
+    assert!(matches!(result, Err(ContractError::InvalidIbcDenom { .. })));
+}
+
+const PLACE_LIMIT_ORDER_RESPONSE: &str = "/neutron.dex.MsgPlaceLimitOrderResponse";
+const WITHDRAW_FILLED_RESPONSE: &str = "/neutron.dex.MsgWithdrawFilledLimitOrderResponse";
+const CANCEL_LIMIT_ORDER_RESPONSE: &str = "/neutron.dex.MsgCancelLimitOrderResponse";
+
+fn place_order_result(placed: u128, taker_in: u128, taker_out: u128) -> SubMsgResult {
+    ok_result(
+        PLACE_LIMIT_ORDER_RESPONSE,
+        MsgPlaceLimitOrderResponse {
+            tranche_key: "tranche".to_string(),
+            coin_in: proto_coin("uusdc", placed),
+            taker_coin_in: proto_coin("uusdc", taker_in),
+            taker_coin_out: proto_coin("untrn", taker_out),
+        },
+    )
+}
+
+// places a just in time order of 1000 uusdc, 400 fill as taker for 800 untrn and 600 rest
+// in a tranche that has expired by the next run
+fn place_maker_order(deps: &mut MockDeps) -> Response {
+    deposit(deps, "alice", 1000, ScheduleOrderType::JustInTime).unwrap();
+    let response = run(deps);
+    let place = &sub_msgs(&response, 1)[0];
+    reply_to(deps, place, place_order_result(1000, 400, 800)).unwrap()
+}
+
+#[test_case(ScheduleOrderType::GoodTilTime { expiry_seconds: 60 }; "good til time")]
+#[test_case(ScheduleOrderType::JustInTime; "just in time")]
+fn test_maker_order_is_received_by_contract(order_type: ScheduleOrderType) {
+    let mut deps = setup(instantiate_msg());
+    deposit(&mut deps, "alice", 1000, order_type).unwrap();
+    let response = run(&mut deps);
+    let place: MsgPlaceLimitOrder = decode_msg(&sub_msgs(&response, 1)[0].msg);
+    assert_eq!(place.receiver, mock_env().contract.address.to_string());
+}
+
+#[test]
+fn test_maker_order_placed() {
+    let mut deps = setup(instantiate_msg());
+    let response = place_maker_order(&mut deps);
+
+    // the contract forwards the taker fill and the rest of the order rests in the tranche
+    assert_eq!(
+        sent_to(&response, "alice"),
+        vec![Coin::new(800u128, "untrn")]
+    );
+    let schedule = schedule(&deps, 0).unwrap();
+    assert_eq!(schedule.remaining_amount, Uint128::zero());
+    assert_eq!(
+        schedule.open_tranches,
+        vec![OpenTranche {
+            tranche_key: "tranche".to_string(),
+            amount: Uint128::new(600),
+            expiration: mock_env().block.time.seconds(),
+            withdrawn: false,
+        }]
+    );
+}
+
+#[test]
+fn test_maker_tranche_expired() {
+    let mut deps = setup(instantiate_msg());
+    place_maker_order(&mut deps);
+
+    let response = run(&mut deps);
+    let withdraw: MsgWithdrawFilledLimitOrder = decode_msg(&sub_msgs(&response, 4)[0].msg);
+    let cancel: MsgCancelLimitOrder = decode_msg(&sub_msgs(&response, 5)[0].msg);
+    assert_eq!(withdraw.tranche_key, "tranche");
+    assert_eq!(cancel.tranche_key, "tranche");
+    assert_eq!(withdraw.creator, mock_env().contract.address.to_string());
+    // the schedule has nothing left to place until its tranche is settled
+    assert!(sub_msgs(&response, 1).is_empty());
+}
+
+fn withdraw_result(filled: u128) -> SubMsgResult {
+    ok_result(
+        WITHDRAW_FILLED_RESPONSE,
+        MsgWithdrawFilledLimitOrderResponse {
+            taker_coin_out: proto_coin("untrn", filled),
+            maker_coin_out: None,
+        },
+    )
+}
+
+fn cancel_result(unfilled: u128) -> SubMsgResult {
+    ok_result(
+        CANCEL_LIMIT_ORDER_RESPONSE,
+        MsgCancelLimitOrderResponse {
+            taker_coin_out: None,
+            maker_coin_out: proto_coin("uusdc", unfilled),
+        },
+    )
+}
+
+#[test]
+fn test_maker_tranche_withdrawn_and_cancelled() {
+    let mut deps = setup(instantiate_msg());
+    place_maker_order(&mut deps);
+    let response = run(&mut deps);
+
+    // the filled part is forwarded to the owner
+    let withdrawn = reply_to(&mut deps, &sub_msgs(&response, 4)[0], withdraw_result(300)).unwrap();
+    assert_eq!(
+        sent_to(&withdrawn, "alice"),
+        vec![Coin::new(300u128, "untrn")]
+    );
+    assert!(schedule(&deps, 0).unwrap().open_tranches[0].withdrawn);
+
+    // the unfilled part is credited back to the schedule and the tranche closed
+    let cancelled = reply_to(&mut deps, &sub_msgs(&response, 5)[0], cancel_result(450)).unwrap();
+    assert!(sent_to(&cancelled, "alice").is_empty());
+    let schedule = schedule(&deps, 0).unwrap();
+    assert_eq!(schedule.remaining_amount, Uint128::new(450));
+    assert!(schedule.open_tranches.is_empty());
+}
+
+#[test_case(true => true; "after a withdraw the tranche is settled")]
+#[test_case(false => false; "without a withdraw the tranche stays tracked")]
+fn test_maker_tranche_cancel_error(withdrawn: bool) -> bool {
+    let mut deps = setup(instantiate_msg());
+    place_maker_order(&mut deps);
+    let response = run(&mut deps);
+
+    let withdraw_reply = match withdrawn {
+        true => withdraw_result(600),
+        false => SubMsgResult::Err("tranche not found".to_string()),
+    };
+    reply_to(&mut deps, &sub_msgs(&response, 4)[0], withdraw_reply).unwrap();
+    let cancelled = reply_to(
+        &mut deps,
+        &sub_msgs(&response, 5)[0],
+        SubMsgResult::Err("active limit order not found".to_string()),
+    )
+    .unwrap();
+
+    let closed = schedule(&deps, 0).map_or(true, |schedule| schedule.open_tranches.is_empty());
+    assert_eq!(
+        attribute(&cancelled, "tranche_closed"),
+        Some(closed.to_string())
+    );
+    closed
+}
+
+// a capped run places less than the schedule's due amount, filling all of it is a full fill
+#[test]
+fn test_fill_measured_against_placed_amount() {
+    let mut deps = setup(InstantiateMsg {
+        run_cap: Some(RunCap::Absolute {
+            amount: Uint128::new(400),
+        }),
+        ..instantiate_msg()
+    });
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    let response = run(&mut deps);
+    let place = &sub_msgs(&response, 1)[0];
+    assert_eq!(
+        decode_msg::<MsgPlaceLimitOrder>(&place.msg).amount_in,
+        "400"
+    );
+
+    let replied = reply_to(&mut deps, place, place_order_result(400, 400, 800)).unwrap();
+    assert_eq!(attribute(&replied, "taker_fill"), Some("full".to_string()));
+    assert_eq!(
+        schedule(&deps, 0).unwrap().remaining_amount,
+        Uint128::new(600)
+    );
+}
+
+#[test_case(400, "partial"; "partial fill")]
+#[test_case(0, "none"; "no fill")]
+fn test_immediate_or_cancel_fill(taker_in: u128, fill: &str) {
+    let mut deps = setup(instantiate_msg());
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    let response = run(&mut deps);
+    let replied = reply_to(
+        &mut deps,
+        &sub_msgs(&response, 1)[0],
+        place_order_result(taker_in, taker_in, taker_in * 2),
+    )
+    .unwrap();
+    assert_eq!(attribute(&replied, "taker_fill"), Some(fill.to_string()));
+    // only what was swapped is spent
+    assert_eq!(
+        schedule(&deps, 0).unwrap().remaining_amount,
+        Uint128::new(1000 - taker_in)
+    );
+}
+
+#[test]
+fn test_fill_or_kill_filled() {
+    let mut deps = setup(instantiate_msg());
+    deposit(&mut deps, "alice", 1000, ScheduleOrderType::FillOrKill).unwrap();
+    let response = run(&mut deps);
+    let place = &sub_msgs(&response, 1)[0];
+    assert_eq!(
+        decode_msg::<MsgPlaceLimitOrder>(&place.msg).order_type,
+        i32::from(LimitOrderType::FillOrKill)
+    );
+
+    let replied = reply_to(&mut deps, place, place_order_result(1000, 1000, 2000)).unwrap();
+    assert_eq!(attribute(&replied, "taker_fill"), Some("full".to_string()));
+    assert!(schedule(&deps, 0).is_none());
+}
+
+#[test]
+fn test_fill_or_kill_killed() {
+    let mut deps = setup(instantiate_msg());
+    deposit(&mut deps, "alice", 1000, ScheduleOrderType::FillOrKill).unwrap();
+    let response = run(&mut deps);
+    let replied = reply_to(
+        &mut deps,
+        &sub_msgs(&response, 1)[0],
+        SubMsgResult::Err(
+            "fill or kill limit order couldn't be executed in its entirety".to_string(),
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        attribute(&replied, "action"),
+        Some("place_limit_order_reply_error".to_string())
+    );
+    // nothing left the contract, the failure is counted against the schedule
+    let schedule = schedule(&deps, 0).unwrap();
+    assert_eq!(schedule.remaining_amount, Uint128::new(1000));
+    assert_eq!(schedule.consecutive_failures, 1);
+}
+
+// a just in time order that does not fill immediately only rests for the current block
+#[test]
+fn test_just_in_time_order() {
+    let mut deps = setup(instantiate_msg());
+    deposit(&mut deps, "alice", 1000, ScheduleOrderType::JustInTime).unwrap();
+    let response = run(&mut deps);
+    let place = &sub_msgs(&response, 1)[0];
+    let order: MsgPlaceLimitOrder = decode_msg(&place.msg);
+    assert_eq!(order.order_type, i32::from(LimitOrderType::JustInTime));
+    assert_eq!(order.expiration_time, None);
+
+    let replied = reply_to(&mut deps, place, place_order_result(1000, 0, 0)).unwrap();
+    // a resting maker order is not a failed fill
+    assert_eq!(attribute(&replied, "taker_fill"), Some("none".to_string()));
+    let schedule = schedule(&deps, 0).unwrap();
+    assert_eq!(schedule.consecutive_failures, 0);
+    assert_eq!(schedule.open_tranches[0].amount, Uint128::new(1000));
+    assert_eq!(
+        schedule.open_tranches[0].expiration,
+        mock_env().block.time.seconds()
+    );
+}
+
+// replies to a venue part of schedule 0 placed by a run
+fn venue_reply(
+    deps: &mut MockDeps,
+    amount_in: u128,
+    last_part: bool,
+    result: SubMsgResult,
+) -> Response {
+    let payload = ReplyPayload::VenueSwap {
+        swap: PendingVenueSwap {
+            schedule_id: 0,
+            amount_in: Uint128::new(amount_in),
+            multi_hop: false,
+            last_part,
+        },
+    };
+    let sub_msg = payload
+        .sub_msg(BankMsg::Burn {
+            amount: vec![Coin::new(amount_in, "uusdc")],
+        })
+        .unwrap();
+    reply_to(deps, &sub_msg, result).unwrap()
+}
+
+#[test]
+fn test_venue_fills_aggregated() {
+    let mut deps = setup(instantiate_msg());
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+
+    venue_reply(&mut deps, 600, false, place_order_result(600, 600, 1200));
+    assert_eq!(
+        VENUE_FILLS.load(&deps.storage, 0).unwrap(),
+        (Uint128::new(600), Uint128::new(1200))
+    );
+    assert_eq!(
+        schedule(&deps, 0).unwrap().remaining_amount,
+        Uint128::new(400)
+    );
+
+    let response = venue_reply(&mut deps, 400, true, place_order_result(300, 300, 600));
+    assert_eq!(
+        attribute(&response, "total_amount_in"),
+        Some("900".to_string())
+    );
+    assert_eq!(
+        attribute(&response, "total_amount_out"),
+        Some("1800".to_string())
+    );
+    assert!(VENUE_FILLS.may_load(&deps.storage, 0).unwrap().is_none());
+    let schedule = schedule(&deps, 0).unwrap();
+    assert_eq!(schedule.remaining_amount, Uint128::new(100));
+    assert_eq!(schedule.consecutive_failures, 0);
+}
+
+// a run only fails if none of its venue parts filled, and counts as one failure
+#[test]
+fn test_venue_parts_failed() {
+    let mut deps = setup(instantiate_msg());
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+
+    venue_reply(
+        &mut deps,
+        600,
+        false,
+        SubMsgResult::Err("no liquidity".to_string()),
+    );
+    let response = venue_reply(&mut deps, 400, true, place_order_result(0, 0, 0));
+    assert_eq!(attribute(&response, "failure"), Some("no_fill".to_string()));
+    assert!(VENUE_FILLS.may_load(&deps.storage, 0).unwrap().is_none());
+    let schedule = schedule(&deps, 0).unwrap();
+    assert_eq!(schedule.remaining_amount, Uint128::new(1000));
+    assert_eq!(schedule.consecutive_failures, 1);
+}
+
+// runs a batch of the due schedules and replies with its fill
+fn run_batch(deps: &mut MockDeps, taker_in: u128, taker_out: u128) -> Response {
+    let response = run(deps);
+    let batch = sub_msgs(&response, 3);
+    assert_eq!(batch.len(), 1);
+    reply_to(
+        deps,
+        &batch[0],
+        place_order_result(taker_in, taker_in, taker_out),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_batch_payout_and_dust() {
+    let mut deps = setup(InstantiateMsg {
+        batch_mode: Some(true),
+        ..instantiate_msg()
+    });
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    deposit(&mut deps, "bob", 2000, ScheduleOrderType::ImmediateOrCancel).unwrap();
+
+    // half of the batch fills, each schedule is debited and paid by its share of the input
+    let response = run_batch(&mut deps, 1500, 1001);
+    assert_eq!(
+        sent_to(&response, "alice"),
+        vec![Coin::new(333u128, "untrn")]
+    );
+    assert_eq!(sent_to(&response, "bob"), vec![Coin::new(667u128, "untrn")]);
+    assert_eq!(attribute(&response, "dust"), Some("1".to_string()));
+    assert_eq!(BATCH_DUST.load(&deps.storage).unwrap(), Uint128::one());
+    assert!(PENDING_BATCH.load(&deps.storage).unwrap().is_empty());
+    assert_eq!(
+        schedule(&deps, 0).unwrap().remaining_amount,
+        Uint128::new(500)
+    );
+    assert_eq!(
+        schedule(&deps, 1).unwrap().remaining_amount,
+        Uint128::new(1500)
+    );
+
+    // the dust is paid out with the next batch
+    let response = run_batch(&mut deps, 2000, 1001);
+    assert_eq!(
+        sent_to(&response, "alice"),
+        vec![Coin::new(250u128, "untrn")]
+    );
+    assert_eq!(sent_to(&response, "bob"), vec![Coin::new(751u128, "untrn")]);
+    assert_eq!(BATCH_DUST.load(&deps.storage).unwrap(), Uint128::one());
+    assert!(schedule(&deps, 0).is_none());
+    assert!(schedule(&deps, 1).is_none());
+}
+
+// a schedule of 1000 uusdc retried at 10, 25 and 50 bps while its orders fill nothing
+fn laddered_deps(msg: InstantiateMsg) -> MockDeps {
+    let mut deps = setup(msg);
+    execute_as(
+        &mut deps,
+        "alice",
+        &[Coin::new(1000u128, "uusdc")],
+        ExecuteMsg::DepositDca {
+            max_sell_amount: Uint128::new(1000),
+            max_slippage_basis_points: 50,
+            order_type: None,
+            max_average_slippage_basis_points: None,
+            target_denom: None,
+            side: None,
+            slippage_ladder: Some(vec![10, 25, 50]),
+            tranche_ladder: None,
+        },
+    )
+    .unwrap();
+    deps
+}
+
+// the encoded limit sell price of a placed order
+fn limit_price(sub_msg: &SubMsg) -> String {
+    let order: MsgPlaceLimitOrder = decode_msg(&sub_msg.msg);
+    order.limit_sell_price.unwrap()
+}
+
+// the limit sell price of an order paying at most price uusdc per untrn, both have 6 decimals
+fn sell_price(price: Decimal) -> String {
+    to_dex_price(cross_price(Decimal::one(), price).unwrap()).unwrap()
+}
+
+#[test_case(None, None; "single order")]
+#[test_case(Some(true), None; "batch mode")]
+#[test_case(None, Some(vec!["uusdc", "untrn"]); "venues")]
+fn test_slippage_ladder_escalates(batch_mode: Option<bool>, venue: Option<Vec<&str>>) {
+    // laddered schedules are neither batched nor split across venues
+    let mut deps = laddered_deps(InstantiateMsg {
+        batch_mode,
+        venues: venue.map(|hops| {
+            vec![Venue {
+                hops: hops.iter().map(|hop| hop.to_string()).collect(),
+            }]
+        }),
+        ..instantiate_msg()
+    });
+    let price = Decimal::percent(50);
+    let response = run(&mut deps);
+    let mut order = sub_msgs(&response, 1)[0].clone();
+    assert_eq!(
+        limit_price(&order),
+        sell_price(apply_slippage(price, 10).unwrap())
+    );
+
+    // each order that fills nothing is placed again at the next step
+    for (step, slippage_basis_points) in [(1, 25), (2, 50)] {
+        let response = reply_to(&mut deps, &order, place_order_result(0, 0, 0)).unwrap();
+        assert_eq!(attribute(&response, "ladder_step"), Some(step.to_string()));
+        order = sub_msgs(&response, 1)[0].clone();
+        assert_eq!(
+            limit_price(&order),
+            sell_price(apply_slippage(price, slippage_basis_points).unwrap())
+        );
+        assert_eq!(schedule(&deps, 0).unwrap().consecutive_failures, 0);
+    }
+
+    // the failure is only counted once the ladder is exhausted
+    let response = reply_to(&mut deps, &order, place_order_result(0, 0, 0)).unwrap();
+    assert!(sub_msgs(&response, 1).is_empty());
+    assert_eq!(attribute(&response, "failure"), Some("no_fill".to_string()));
+    let schedule = schedule(&deps, 0).unwrap();
+    assert_eq!(schedule.consecutive_failures, 1);
+    assert_eq!(schedule.remaining_amount, Uint128::new(1000));
+}
+
+#[test]
+fn test_slippage_ladder_step_filled() {
+    let mut deps = laddered_deps(instantiate_msg());
+    let response = run(&mut deps);
+    let response = reply_to(
+        &mut deps,
+        &sub_msgs(&response, 1)[0],
+        SubMsgResult::Err("limit price exceeded".to_string()),
+    )
+    .unwrap();
+    assert_eq!(attribute(&response, "ladder_step"), Some("1".to_string()));
+
+    let response = reply_to(
+        &mut deps,
+        &sub_msgs(&response, 1)[0],
+        place_order_result(1000, 1000, 1990),
+    )
+    .unwrap();
+    assert_eq!(
+        attribute(&response, "ladder_step_filled"),
+        Some("1".to_string())
+    );
+    assert!(schedule(&deps, 0).is_none());
+}
+
+#[allow(deprecated)]
+fn empty_result() -> SubMsgResult {
+    SubMsgResult::Ok(SubMsgResponse {
+        events: vec![],
+        data: None,
+        msg_responses: vec![],
+    })
+}
+
+// a reply that cannot be decoded counts as a failed order and leaves the schedule as it was
+#[test]
+fn test_undecodable_reply_counts_as_failure() {
+    let mut deps = setup(instantiate_msg());
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    let response = run(&mut deps);
+    let replied = reply_to(&mut deps, &sub_msgs(&response, 1)[0], empty_result()).unwrap();
+    assert_eq!(
+        attribute(&replied, "failure"),
+        Some("invalid_reply".to_string())
+    );
+    let schedule = schedule(&deps, 0).unwrap();
+    assert_eq!(schedule.remaining_amount, Uint128::new(1000));
+    assert_eq!(schedule.consecutive_failures, 1);
+}
+
+#[test]
+fn test_undecodable_venue_reply_counts_as_failure() {
+    let mut deps = setup(instantiate_msg());
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    let response = venue_reply(&mut deps, 1000, true, empty_result());
+    assert_eq!(
+        attribute(&response, "action"),
+        Some("venue_swap_reply_error".to_string())
+    );
+    assert_eq!(
+        attribute(&response, "failure"),
+        Some("invalid_reply".to_string())
+    );
+    let schedule = schedule(&deps, 0).unwrap();
+    assert_eq!(schedule.remaining_amount, Uint128::new(1000));
+    assert_eq!(schedule.consecutive_failures, 1);
+}
+
+// the batch is cleared and counted as a failure of each of its schedules
+#[test]
+fn test_undecodable_batch_reply_counts_as_failure() {
+    let mut deps = setup(InstantiateMsg {
+        batch_mode: Some(true),
+        ..instantiate_msg()
+    });
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    deposit(&mut deps, "bob", 2000, ScheduleOrderType::ImmediateOrCancel).unwrap();
+    let response = run(&mut deps);
+    let replied = reply_to(&mut deps, &sub_msgs(&response, 3)[0], empty_result()).unwrap();
+    assert_eq!(
+        attribute(&replied, "failure"),
+        Some("invalid_reply".to_string())
+    );
+    assert!(PENDING_BATCH.load(&deps.storage).unwrap().is_empty());
+    for schedule_id in [0, 1] {
+        let schedule = schedule(&deps, schedule_id).unwrap();
+        assert_eq!(schedule.consecutive_failures, 1);
+        assert_eq!(
+            schedule.remaining_amount,
+            Uint128::new(1000 * (schedule_id + 1))
+        );
+    }
+}
+
+// failed batches park their schedules like single orders, a batch that fills resets them
+#[test]
+fn test_batch_failures_park_schedules() {
+    let mut deps = setup(InstantiateMsg {
+        batch_mode: Some(true),
+        max_consecutive_failures: Some(2),
+        ..instantiate_msg()
+    });
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    deposit(&mut deps, "bob", 2000, ScheduleOrderType::ImmediateOrCancel).unwrap();
+    let fail_batch = |deps: &mut MockDeps| {
+        let response = run(deps);
+        reply_to(
+            deps,
+            &sub_msgs(&response, 3)[0],
+            SubMsgResult::Err("no liquidity".to_string()),
+        )
+        .unwrap()
+    };
+
+    fail_batch(&mut deps);
+    run_batch(&mut deps, 300, 600);
+    assert_eq!(schedule(&deps, 0).unwrap().consecutive_failures, 0);
+    assert_eq!(schedule(&deps, 1).unwrap().consecutive_failures, 0);
+
+    fail_batch(&mut deps);
+    let response = fail_batch(&mut deps);
+    let parked = response
+        .events
+        .iter()
+        .filter(|event| event.ty == "schedule_parked")
+        .count();
+    assert_eq!(parked, 2);
+    assert!(schedule(&deps, 0).unwrap().parked);
+    assert!(schedule(&deps, 1).unwrap().parked);
+    assert!(sub_msgs(&run(&mut deps), 3).is_empty());
+}
+
+// a fill above what the schedule holds is capped at its remaining amount
+#[test]
+fn test_reply_debit_capped() {
+    let mut deps = setup(instantiate_msg());
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    let response = run(&mut deps);
+    let replied = reply_to(
+        &mut deps,
+        &sub_msgs(&response, 1)[0],
+        place_order_result(1200, 1200, 2400),
+    )
+    .unwrap();
+    assert_eq!(attribute(&replied, "amount_in"), Some("1000".to_string()));
+    assert!(schedule(&deps, 0).is_none());
+}
+
+#[test]
+fn test_reply_for_closed_taker_schedule() {
+    let mut deps = setup(instantiate_msg());
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    let response = run(&mut deps);
+    execute_as(&mut deps, "alice", &[], ExecuteMsg::WithdrawAll {}).unwrap();
+
+    // the taker proceeds were received by the owner, there is nothing left to settle
+    let replied = reply_to(
+        &mut deps,
+        &sub_msgs(&response, 1)[0],
+        place_order_result(1000, 1000, 2000),
+    )
+    .unwrap();
+    assert_eq!(
+        attribute(&replied, "schedule_closed"),
+        Some("true".to_string())
+    );
+    assert!(replied.messages.is_empty());
+}
+
+#[test]
+fn test_reply_for_closed_maker_schedule() {
+    let mut deps = setup(instantiate_msg());
+    deposit(&mut deps, "alice", 1000, ScheduleOrderType::JustInTime).unwrap();
+    let response = run(&mut deps);
+    execute_as(&mut deps, "alice", &[], ExecuteMsg::WithdrawAll {}).unwrap();
+
+    // the immediate fill is forwarded and the resting tranche pulled for the owner
+    let replied = reply_to(
+        &mut deps,
+        &sub_msgs(&response, 1)[0],
+        place_order_result(1000, 400, 800),
+    )
+    .unwrap();
+    assert_eq!(
+        sent_to(&replied, "alice"),
+        vec![Coin::new(800u128, "untrn")]
+    );
+    let withdrawn = reply_to(&mut deps, &sub_msgs(&replied, 4)[0], withdraw_result(200)).unwrap();
+    assert_eq!(
+        sent_to(&withdrawn, "alice"),
+        vec![Coin::new(200u128, "untrn")]
+    );
+    let cancelled = reply_to(&mut deps, &sub_msgs(&replied, 5)[0], cancel_result(500)).unwrap();
+    assert_eq!(
+        sent_to(&cancelled, "alice"),
+        vec![Coin::new(500u128, "uusdc")]
+    );
+}
+
+// a schedule with 2 failed runs in a row is parked and skipped by later runs
+fn parked_deps() -> MockDeps {
+    let mut deps = setup(InstantiateMsg {
+        max_consecutive_failures: Some(2),
+        ..instantiate_msg()
+    });
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    for failures in 1..=2u64 {
+        let response = run(&mut deps);
+        let replied = reply_to(
+            &mut deps,
+            &sub_msgs(&response, 1)[0],
+            SubMsgResult::Err("no liquidity".to_string()),
+        )
+        .unwrap();
+        let parked = replied
+            .events
+            .iter()
+            .any(|event| event.ty == "schedule_parked");
+        assert_eq!(parked, failures == 2);
+    }
+    deps
+}
+
+#[test]
+fn test_schedule_parked_after_failures() {
+    let mut deps = parked_deps();
+    let schedule = schedule(&deps, 0).unwrap();
+    assert!(schedule.parked);
+    assert_eq!(schedule.consecutive_failures, 2);
+    assert!(sub_msgs(&run(&mut deps), 1).is_empty());
+}
+
+#[test]
+fn test_resume_schedule() {
+    let mut deps = parked_deps();
+    let resume = |schedule_id| ExecuteMsg::ResumeSchedule { schedule_id };
+    assert_eq!(
+        execute_as(&mut deps, "bob", &[], resume(0)).unwrap_err(),
+        ContractError::Unauthorized
+    );
+    assert_eq!(
+        execute_as(&mut deps, "alice", &[], resume(9)).unwrap_err(),
+        ContractError::ScheduleNotFound
+    );
+
+    execute_as(&mut deps, "alice", &[], resume(0)).unwrap();
+    let schedule = schedule(&deps, 0).unwrap();
+    assert!(!schedule.parked);
+    assert_eq!(schedule.consecutive_failures, 0);
+    assert_eq!(sub_msgs(&run(&mut deps), 1).len(), 1);
+}
+
+// a schedule of 1000 uusdc placed in two tranche steps of 950 and 50 with a min tradable
+// amount of 100. The first part leaves the schedule below the min while the second part is
+// still in flight, its dust is only refunded once the last part has replied
+#[test]
+fn test_dust_closed_after_last_part() {
+    let mut deps = setup(InstantiateMsg {
+        min_tradable_amounts: Some(vec![Coin::new(100u128, "uusdc")]),
+        ..instantiate_msg()
+    });
+    execute_as(
+        &mut deps,
+        "alice",
+        &[Coin::new(1000u128, "uusdc")],
+        ExecuteMsg::DepositDca {
+            max_sell_amount: Uint128::new(1000),
+            max_slippage_basis_points: 50,
+            order_type: None,
+            max_average_slippage_basis_points: None,
+            target_denom: None,
+            side: None,
+            slippage_ladder: None,
+            tranche_ladder: Some(vec![
+                TrancheStep {
+                    fraction: Decimal::percent(95),
+                    slippage_basis_points: 10,
+                },
+                TrancheStep {
+                    fraction: Decimal::percent(5),
+                    slippage_basis_points: 50,
+                },
+            ]),
+        },
+    )
+    .unwrap();
+    let response = run(&mut deps);
+    let parts = sub_msgs(&response, 2);
+    assert_eq!(parts.len(), 2);
+
+    let first = reply_to(&mut deps, &parts[0], place_order_result(950, 950, 1900)).unwrap();
+    assert!(sent_to(&first, "alice").is_empty());
+    assert_eq!(
+        schedule(&deps, 0).unwrap().remaining_amount,
+        Uint128::new(50)
+    );
+
+    let last = reply_to(&mut deps, &parts[1], place_order_result(30, 30, 60)).unwrap();
+    assert_eq!(sent_to(&last, "alice"), vec![Coin::new(20u128, "uusdc")]);
+    assert_eq!(attribute(&last, "total_amount_in"), Some("980".to_string()));
+    assert!(schedule(&deps, 0).is_none());
+}
+
+#[test_case(vec![Coin::new(100u128, "uatom")]; "not a pair denom")]
+#[test_case(vec![Coin::new(100u128, "uusdc"), Coin::new(200u128, "uusdc")]; "duplicate denom")]
+fn test_instantiate_rejects_min_tradable_amounts(min_tradable_amounts: Vec<Coin>) {
+    let mut deps = mock_deps();
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        message_info(&addr("owner"), &[]),
+        InstantiateMsg {
+            min_tradable_amounts: Some(min_tradable_amounts),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::MalformedInput { .. }));
+}
+
+fn run_mine(deps: &mut MockDeps, sender: &str) -> Result<Response, ContractError> {
+    execute_as(deps, sender, &[], ExecuteMsg::RunMySchedules {})
+}
+
+// alice owns schedules 0 and 2, bob owns schedule 1
+fn shared_deps(msg: InstantiateMsg) -> MockDeps {
+    let mut deps = setup(msg);
+    for owner in ["alice", "bob", "alice"] {
+        deposit(&mut deps, owner, 1000, ScheduleOrderType::ImmediateOrCancel).unwrap();
+    }
+    deps
+}
+
+#[test]
+fn test_run_my_schedules_only_runs_sender() {
+    let mut deps = shared_deps(instantiate_msg());
+    let bob_schedule = schedule(&deps, 1).unwrap();
+
+    let response = run_mine(&mut deps, "alice").unwrap();
+    let orders = sub_msgs(&response, 1);
+    assert_eq!(orders.len(), 2);
+    for order in orders.iter() {
+        let order: MsgPlaceLimitOrder = decode_msg(&order.msg);
+        assert_eq!(order.receiver, addr("alice").to_string());
+    }
+
+    // the other schedules are stored back unchanged and in the order they were deposited
+    let ids: Vec<u128> = SCHEDULES
+        .load(&deps.storage)
+        .unwrap()
+        .schedules
+        .iter()
+        .map(|schedule| schedule.id)
+        .collect();
+    assert_eq!(ids, vec![0, 1, 2]);
+    assert_eq!(schedule(&deps, 1).unwrap(), bob_schedule);
+}
+
+// the run cap is shared by the runs of a block and renewed by the next block
+#[test]
+fn test_run_my_schedules_shares_run_cap() {
+    let mut deps = shared_deps(InstantiateMsg {
+        run_cap: Some(RunCap::Absolute {
+            amount: Uint128::new(1500),
+        }),
+        ..instantiate_msg()
+    });
+    let placed = |response: &Response| -> Vec<String> {
+        sub_msgs(response, 1)
+            .iter()
+            .map(|order| decode_msg::<MsgPlaceLimitOrder>(&order.msg).amount_in)
+            .collect()
+    };
+
+    let response = run_mine(&mut deps, "alice").unwrap();
+    assert_eq!(placed(&response), vec!["750", "750"]);
+    let response = run_mine(&mut deps, "alice").unwrap();
+    assert!(placed(&response).is_empty());
+    assert_eq!(attribute(&response, "run_cap"), Some("0".to_string()));
+
+    let mut env = mock_env();
+    env.block.height += 1;
+    let response = execute(
+        deps.as_mut(),
+        env,
+        message_info(&addr("bob"), &[]),
+        ExecuteMsg::RunMySchedules {},
+    )
+    .unwrap();
+    assert_eq!(placed(&response), vec!["1000"]);
+}
+
+#[test]
+fn test_run_my_schedules_without_schedules() {
+    let mut deps = shared_deps(instantiate_msg());
+    let response = run_mine(&mut deps, "carol").unwrap();
+    assert!(sub_msgs(&response, 1).is_empty());
+    assert_eq!(SCHEDULES.load(&deps.storage).unwrap().schedules.len(), 3);
+}
+
+#[test]
+fn test_run_my_schedules_depeg_pause() {
+    let mut deps = depeg_deps("0.9");
+    let response = run_mine(&mut deps, "alice").unwrap();
+    assert_eq!(
+        attribute(&response, "action"),
+        Some("run_schedules_paused".to_string())
+    );
+    assert!(sub_msgs(&response, 1).is_empty());
+}
+
+#[test]
+fn test_run_my_schedules_without_oracle_price() {
+    let mut deps = shared_deps(instantiate_msg());
+    deps.querier.prices.remove("NTRN/USD");
+    assert!(run_mine(&mut deps, "alice").is_err());
+}
+
+// the mock chain has no DEX pool to compare the oracle price against
+#[test]
+fn test_run_my_schedules_checks_dex_deviation() {
+    let mut deps = shared_deps(InstantiateMsg {
+        max_dex_deviation_bps: Some(100),
+        ..instantiate_msg()
+    });
+    assert!(run_mine(&mut deps, "alice").is_err());
+}
+
+// uatom bought along uusdc -> untrn -> uatom at 5 uusdc per uatom
+fn multi_hop_deps() -> MockDeps {
+    let mut deps = setup(InstantiateMsg {
+        assets: vec![
+            asset("untrn", "NTRN", true),
+            asset("uusdc", "USDC", true),
+            asset("uatom", "ATOM", true),
+        ],
+        ..instantiate_msg()
+    });
+    execute_as(
+        &mut deps,
+        "owner",
+        &[],
+        ExecuteMsg::SetRoute {
+            target_denom: "uatom".to_string(),
+            hops: Some(vec![
+                "uusdc".to_string(),
+                "untrn".to_string(),
+                "uatom".to_string(),
+            ]),
+        },
+    )
+    .unwrap();
+    deps
+}
+
+// the exit limit price is at least 1 / (5 * 1.01) uatom per uusdc, encoded like every
+// other DEX price
+#[test]
+fn test_multi_hop_exit_limit_price() {
+    let mut deps = multi_hop_deps();
+    execute_as(
+        &mut deps,
+        "alice",
+        &[Coin::new(1000u128, "uusdc")],
+        ExecuteMsg::DepositDca {
+            max_sell_amount: Uint128::new(1000),
+            max_slippage_basis_points: 100,
+            order_type: None,
+            max_average_slippage_basis_points: None,
+            target_denom: Some("uatom".to_string()),
+            side: None,
+            slippage_ladder: None,
+            tranche_ladder: None,
+        },
+    )
+    .unwrap();
+    let response = run(&mut deps);
+    let swap: MsgMultiHopSwap = decode_msg(&sub_msgs(&response, 2)[0].msg);
+    assert_eq!(swap.routes[0].hops, vec!["uusdc", "untrn", "uatom"]);
+    assert_eq!(swap.exit_limit_price, "198019801980198019000000000");
+}
+
+// a venue routed through uatom has no depth in the mock, so it takes the whole run. Its exit
+// limit price is at least 1 / (0.5 * 1.01) untrn per uusdc
+#[test]
+fn test_venue_multi_hop_exit_limit_price() {
+    let mut deps = setup(InstantiateMsg {
+        venues: Some(vec![Venue {
+            hops: vec![
+                "uusdc".to_string(),
+                "uatom".to_string(),
+                "untrn".to_string(),
+            ],
+        }]),
+        ..instantiate_msg()
+    });
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    let response = run(&mut deps);
+    let parts = sub_msgs(&response, 2);
+    assert_eq!(parts.len(), 1);
+    let swap: MsgMultiHopSwap = decode_msg(&parts[0].msg);
+    assert_eq!(swap.amount_in, "1000");
+    assert_eq!(swap.exit_limit_price, "1980198019801980198000000000");
+}
+
+// a direct venue without depth takes the whole run, its part is simulated like a single order
+fn checked_venue_deps(hops: &[&str], downsize: bool) -> MockDeps {
+    let mut deps = setup(InstantiateMsg {
+        venues: Some(vec![Venue {
+            hops: hops.iter().map(|hop| hop.to_string()).collect(),
+        }]),
+        liquidity_check: Some(LiquidityCheck {
+            min_fill_ratio: Decimal::percent(80),
+            downsize,
+        }),
+        ..instantiate_msg()
+    });
+    deposit(
+        &mut deps,
+        "alice",
+        1000,
+        ScheduleOrderType::ImmediateOrCancel,
+    )
+    .unwrap();
+    deps
+}
+
+#[test_case(None, false, None; "failed simulation skips")]
+#[test_case(Some(900), false, Some(1000); "enough fill places all")]
+#[test_case(Some(400), false, None; "thin book skips")]
+#[test_case(Some(400), true, Some(400); "thin book downsizes")]
+fn test_venue_part_liquidity_checked(
+    simulated_fill: Option<u128>,
+    downsize: bool,
+    expected: Option<u128>,
+) {
+    let mut deps = checked_venue_deps(&["uusdc", "untrn"], downsize);
+    deps.querier.simulated_fill = simulated_fill.map(Uint128::new);
+    let response = run(&mut deps);
+    let parts = sub_msgs(&response, 2);
+    match expected {
+        Some(amount) => {
+            assert_eq!(parts.len(), 1);
+            let place: MsgPlaceLimitOrder = decode_msg(&parts[0].msg);
+            assert_eq!(place.amount_in, amount.to_string());
+            let payload: ReplyPayload = from_json(parts[0].payload.clone()).unwrap();
+            assert!(matches!(
+                payload,
+                ReplyPayload::VenueSwap { swap } if swap.last_part
+            ));
+        }
+        None => assert!(parts.is_empty()),
+    }
+    assert_eq!(
+        attribute(&response, "schedules_skipped"),
+        Some(u64::from(expected.is_none()).to_string())
+    );
+    assert_eq!(
+        attribute(&response, "schedules_downsized"),
+        Some(u64::from(expected == Some(400)).to_string())
+    );
+}
+
+// multi-hop parts fill all or nothing within their exit limit price and are not simulated
+#[test]
+fn test_venue_multi_hop_part_not_simulated() {
+    let mut deps = checked_venue_deps(&["uusdc", "uatom", "untrn"], false);
+    let response = run(&mut deps);
+    let parts = sub_msgs(&response, 2);
+    assert_eq!(parts.len(), 1);
+    let swap: MsgMultiHopSwap = decode_msg(&parts[0].msg);
+    assert_eq!(swap.amount_in, "1000");
+    assert_eq!(
+        attribute(&response, "schedules_skipped"),
+        Some("0".to_string())
+    );
+}
@@ -0,0 +1,206 @@
+use crate::error::{ContractError, ContractResult};
+use crate::oracle::{aggregate_median, median, PriceFeed};
+use crate::state::{Config, ExecutionVenueConfig, OraclePolicy, PairData, PricePath};
+use crate::utils::{validate_market_enabled, validate_market_providers, validate_price_recent};
+use cosmwasm_std::testing::{mock_dependencies, mock_env};
+use cosmwasm_std::{Addr, Decimal, Deps, Env};
+use neutron_std::shim::Timestamp;
+use neutron_std::types::slinky::{
+    marketmap::v1::{Market, MarketResponse, ProviderConfig, Ticker},
+    oracle::v1::{GetPriceResponse, QuotePrice},
+    types::v1::CurrencyPair,
+};
+use std::str::FromStr;
+use test_case::test_case;
+
+/// Feed returning a fixed price, or PriceIsNil if no price is set.
+struct MockFeed(Option<&'static str>);
+
+impl PriceFeed for MockFeed {
+    fn name(&self) -> String {
+        "mock".to_string()
+    }
+
+    fn price(&self, _deps: Deps, _env: &Env, _config: &Config) -> ContractResult<Decimal> {
+        match self.0 {
+            Some(price) => Ok(Decimal::from_str(price).unwrap()),
+            None => Err(ContractError::PriceIsNil {
+                symbol: "NTRN".to_string(),
+                quote: "USD".to_string(),
+            }),
+        }
+    }
+}
+
+pub fn mock_config() -> Config {
+    Config {
+        pair_data: PairData {
+            denom_ntrn: "untrn".to_string(),
+            denom_usd: "uusdc".to_string(),
+            currency_pair: CurrencyPair {
+                base: "NTRN".to_string(),
+                quote: "USD".to_string(),
+            },
+            pair_id: "untrn<>uusdc".to_string(),
+            price_path: PricePath::Direct {
+                pair: CurrencyPair {
+                    base: "NTRN".to_string(),
+                    quote: "USD".to_string(),
+                },
+            },
+        },
+        oracle_policy: OraclePolicy {
+            max_blocks_old: 10,
+            max_seconds_old: 60,
+            require_enabled: true,
+            min_provider_count: 1,
+        },
+        dex_fallback: None,
+        max_dex_deviation_bps: None,
+        price_aggregation: None,
+        depeg_guard: None,
+        venues: vec![],
+        execution_venue: ExecutionVenueConfig::NeutronDex {},
+        liquidity_check: None,
+        run_cap: None,
+        batch_mode: false,
+        max_consecutive_failures: None,
+        min_tradable_amounts: vec![],
+        owner: Addr::unchecked("owner"),
+        max_schedules: 10,
+    }
+}
+
+fn run_aggregation(prices: Vec<Option<&'static str>>, quorum: u64) -> ContractResult<Decimal> {
+    let deps = mock_dependencies();
+    let feeds: Vec<Box<dyn PriceFeed>> = prices
+        .into_iter()
+        .map(|price| Box::new(MockFeed(price)) as Box<dyn PriceFeed>)
+        .collect();
+    aggregate_median(deps.as_ref(), &mock_env(), &mock_config(), &feeds, quorum)
+}
+
+#[test_case(vec![Some("1.0"), Some("1.0"), Some("1.0")], 3 => Ok(Decimal::from_str("1.0").unwrap()); "all sources agree")]
+#[test_case(vec![Some("1.0"), Some("1.02"), Some("5.0")], 3 => Ok(Decimal::from_str("1.02").unwrap()); "outlier is ignored")]
+#[test_case(vec![Some("0.01"), Some("0.3"), Some("0.31")], 2 => Ok(Decimal::from_str("0.3").unwrap()); "low outlier is ignored")]
+#[test_case(vec![Some("1.0"), Some("2.0")], 2 => Ok(Decimal::from_str("1.5").unwrap()); "even number of sources averages the middle")]
+fn test_aggregate_median_disagreement(
+    prices: Vec<Option<&'static str>>,
+    quorum: u64,
+) -> ContractResult<Decimal> {
+    run_aggregation(prices, quorum)
+}
+
+#[test_case(vec![Some("1.0"), None, Some("1.1")], 2 => Ok(Decimal::from_str("1.05").unwrap()); "one missing source")]
+#[test_case(vec![None, None, Some("1.1")], 1 => Ok(Decimal::from_str("1.1").unwrap()); "single remaining source meets quorum")]
+fn test_aggregate_median_missing_sources(
+    prices: Vec<Option<&'static str>>,
+    quorum: u64,
+) -> ContractResult<Decimal> {
+    run_aggregation(prices, quorum)
+}
+
+#[test_case(vec![Some("1.0"), None, None], 2 => Err(ContractError::QuorumNotReached { available: 1, required: 2 }); "too many missing sources")]
+#[test_case(vec![None, None, None], 1 => Err(ContractError::QuorumNotReached { available: 0, required: 1 }); "no sources")]
+#[test_case(vec![], 1 => Err(ContractError::QuorumNotReached { available: 0, required: 1 }); "no feeds configured")]
+fn test_aggregate_median_quorum_failure(
+    prices: Vec<Option<&'static str>>,
+    quorum: u64,
+) -> ContractResult<Decimal> {
+    run_aggregation(prices, quorum)
+}
+
+#[test_case(vec![] => Err(ContractError::InvalidPrice); "empty")]
+#[test_case(vec!["3.0", "1.0", "2.0"] => Ok(Decimal::from_str("2.0").unwrap()); "unsorted input")]
+fn test_median(prices: Vec<&str>) -> ContractResult<Decimal> {
+    median(
+        prices
+            .into_iter()
+            .map(|price| Decimal::from_str(price).unwrap())
+            .collect(),
+    )
+}
+
+fn market_response(ticker: Option<bool>, providers: usize) -> MarketResponse {
+    MarketResponse {
+        market: Some(Market {
+            ticker: ticker.map(|enabled| Ticker {
+                enabled,
+                ..Default::default()
+            }),
+            provider_configs: vec![ProviderConfig::default(); providers],
+        }),
+    }
+}
+
+fn ntrn_usd() -> CurrencyPair {
+    mock_config().pair_data.currency_pair
+}
+
+#[test_case(Some(true) => Ok(()); "enabled")]
+#[test_case(Some(false) => Err(ContractError::DisabledMarket { symbol: "NTRN".to_string(), quote: "USD".to_string(), location: "x/marketmap".to_string() }); "disabled")]
+#[test_case(None => Err(ContractError::UnsupportedMarket { symbol: "NTRN".to_string(), quote: "USD".to_string(), location: "x/marketmap".to_string() }); "missing ticker")]
+fn test_validate_market_enabled(ticker: Option<bool>) -> ContractResult<()> {
+    let deps = mock_dependencies();
+    validate_market_enabled(
+        &deps.as_ref(),
+        &ntrn_usd(),
+        Some(market_response(ticker, 1)),
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn test_validate_market_enabled_missing_market() {
+    let deps = mock_dependencies();
+    let result = validate_market_enabled(
+        &deps.as_ref(),
+        &ntrn_usd(),
+        Some(MarketResponse { market: None }),
+    );
+    assert!(matches!(
+        result,
+        Err(ContractError::UnsupportedMarket { .. })
+    ));
+}
+
+#[test_case(2, 2 => Ok(()); "enough providers")]
+#[test_case(1, 2 => Err(ContractError::InsufficientProviders { symbol: "NTRN".to_string(), quote: "USD".to_string(), providers: 1, required: 2 }); "too few providers")]
+#[test_case(0, 0 => Ok(()); "no minimum")]
+fn test_validate_market_providers(providers: usize, required: u64) -> ContractResult<()> {
+    let deps = mock_dependencies();
+    validate_market_providers(
+        &deps.as_ref(),
+        &ntrn_usd(),
+        required,
+        Some(market_response(Some(true), providers)),
+    )
+    .map(|_| ())
+}
+
+#[test_case(0 => Ok(()); "same block time")]
+#[test_case(60 => Ok(()); "at the max age")]
+#[test_case(61 => Err(ContractError::PriceTooOldSeconds { symbol: "NTRN".to_string(), quote: "USD".to_string(), max_seconds: 60 }); "older than the max age")]
+fn test_validate_price_recent_seconds(age: u64) -> ContractResult<()> {
+    let deps = mock_dependencies();
+    let env = mock_env();
+    let price_response = GetPriceResponse {
+        price: Some(QuotePrice {
+            price: "1000000".to_string(),
+            block_timestamp: Some(Timestamp {
+                seconds: env.block.time.seconds() as i64 - age as i64,
+                nanos: 0,
+            }),
+            block_height: env.block.height,
+        }),
+        ..Default::default()
+    };
+    validate_price_recent(
+        &deps.as_ref(),
+        &env,
+        &ntrn_usd(),
+        &mock_config().oracle_policy,
+        Some(price_response),
+    )
+    .map(|_| ())
+}
@@ -0,0 +1,61 @@
+use crate::error::ContractError;
+use crate::reply::ReplyPayload;
+use crate::state::{LadderOrder, PendingOrder, PendingTrancheOp, PendingVenueSwap};
+use cosmwasm_std::{Addr, BankMsg, Binary, Decimal, Reply, SubMsgResult, Uint128};
+use test_case::test_case;
+
+fn reply(id: u64, payload: Binary) -> Reply {
+    Reply {
+        id,
+        payload,
+        gas_used: 0,
+        result: SubMsgResult::Err("error".to_string()),
+    }
+}
+
+fn order(schedule_id: u128) -> PendingOrder {
+    PendingOrder {
+        schedule_id,
+        owner: Addr::unchecked("owner"),
+        amount_in: Uint128::new(100),
+        token_out: "untrn".to_string(),
+        maker: false,
+    }
+}
+
+fn tranche_op() -> PendingTrancheOp {
+    PendingTrancheOp {
+        schedule_id: 7,
+        owner: Addr::unchecked("owner"),
+        tranche_key: "key".to_string(),
+    }
+}
+
+#[test_case(ReplyPayload::RunSchedule { order: order(u128::MAX), ladder: None }; "schedule id above u64")]
+#[test_case(ReplyPayload::RunSchedule { order: order(1), ladder: Some(LadderOrder { step: 1, amount_in: Uint128::new(100), price: Decimal::percent(50) }) }; "ladder order")]
+#[test_case(ReplyPayload::VenueSwap { swap: PendingVenueSwap { schedule_id: 1, amount_in: Uint128::new(100), multi_hop: true, last_part: false } }; "venue swap")]
+#[test_case(ReplyPayload::Batch {}; "batch")]
+#[test_case(ReplyPayload::WithdrawFilled { op: tranche_op() }; "withdraw filled")]
+#[test_case(ReplyPayload::CancelLimitOrder { op: tranche_op() }; "cancel limit order")]
+fn test_reply_payload_round_trip(payload: ReplyPayload) {
+    let sub_msg = payload
+        .sub_msg(BankMsg::Send {
+            to_address: "owner".to_string(),
+            amount: vec![],
+        })
+        .unwrap();
+    assert_eq!(sub_msg.id, payload.reply_id());
+    assert_eq!(
+        ReplyPayload::from_reply(&reply(sub_msg.id, sub_msg.payload)).unwrap(),
+        payload
+    );
+}
+
+#[test_case(Binary::default(); "no payload")]
+#[test_case(Binary::from(b"{\"unknown\":{}}"); "unknown kind")]
+fn test_unknown_reply(payload: Binary) {
+    assert_eq!(
+        ReplyPayload::from_reply(&reply(42, payload)),
+        Err(ContractError::UnknownReply { id: 42 })
+    );
+}
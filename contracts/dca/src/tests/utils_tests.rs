@@ -1,6 +1,19 @@
 use crate::error::ContractError;
-use crate::utils::{normalize_price, price_to_tick_index};
-use cosmwasm_std::{Decimal, Int128};
+use crate::oracle_tests::mock_config;
+use crate::state::{
+    LiquidityCheck, OpenTranche, Schedule, ScheduleOrderType, ScheduleSide, Schedules, TrancheStep,
+    DEX_PAIRS,
+};
+use crate::utils::{
+    apply_slippage, checked_fill_amount, classify_order_error, close_dust_schedules, cross_price,
+    discover_route, exit_limit_price, from_base_unit_price, net_intents, normalize_price,
+    parse_prec_dec, price_deviation_bps, price_to_tick_index, pro_rata, run_seed, shuffled_order,
+    split_all, split_by_depth, split_exact, split_pro_rata, to_base_unit_price,
+    validate_denom_format, validate_order_config, validate_route, DenomFormat, Netting,
+    OrderFailure,
+};
+use cosmwasm_std::testing::MockStorage;
+use cosmwasm_std::{Addr, BankMsg, Coin, CosmosMsg, Decimal, Int128, Uint128};
 use std::str::FromStr;
 use test_case::test_case;
 
@@ -40,3 +53,466 @@ fn test_normalize_price(
 ) -> Result<Decimal, ContractError> {
     normalize_price(input_price, input_decimals)
 }
+
+#[test_case("0.3" => Ok(Decimal::from_str("0.3").unwrap()); "short fraction")]
+#[test_case("12" => Ok(Decimal::from_str("12").unwrap()); "whole number")]
+#[test_case("0.333333333333333333333333333" => Ok(Decimal::from_str("0.333333333333333333").unwrap()); "prec dec truncated to 18 decimals")]
+#[test_case("abc" => Err(ContractError::DecimalConversionError); "not a number")]
+fn test_parse_prec_dec(input: &str) -> Result<Decimal, ContractError> {
+    parse_prec_dec(input)
+}
+
+#[test_case("1.0", "1.0" => Ok(0); "equal prices")]
+#[test_case("1.0", "1.01" => Ok(100); "price above reference")]
+#[test_case("1.0", "0.95" => Ok(500); "price below reference")]
+#[test_case("0.3", "0.30001" => Ok(1); "partial basis point rounds up")]
+#[test_case("0", "1.0" => Err(ContractError::InvalidPrice); "zero reference price")]
+fn test_price_deviation_bps(reference_price: &str, price: &str) -> Result<u64, ContractError> {
+    price_deviation_bps(
+        Decimal::from_str(reference_price).unwrap(),
+        Decimal::from_str(price).unwrap(),
+    )
+}
+
+#[test_case(Int128::new(1250000000), 8, Int128::new(40000000), 8 => Ok(Decimal::from_str("31.25").unwrap()); "legs with equal decimals")]
+#[test_case(Int128::new(12500), 3, Int128::new(400000000), 9 => Ok(Decimal::from_str("31.25").unwrap()); "legs with different decimals")]
+#[test_case(Int128::new(1), 6, Int128::new(3), 6 => Ok(Decimal::from_str("0.333333333333333333").unwrap()); "non terminating ratio")]
+#[test_case(Int128::new(1), 6, Int128::zero(), 6 => Err(ContractError::InvalidPrice); "zero quote leg")]
+fn test_cross_price(
+    base_price: Int128,
+    base_decimals: u64,
+    quote_price: Int128,
+    quote_decimals: u64,
+) -> Result<Decimal, ContractError> {
+    cross_price(
+        normalize_price(base_price, base_decimals)?,
+        normalize_price(quote_price, quote_decimals)?,
+    )
+}
+
+#[test_case("0.5", 6, 6 => Ok(Decimal::from_str("0.5").unwrap()); "equal exponents")]
+#[test_case("0.5", 6, 18 => Ok(Decimal::from_str("500000000000").unwrap()); "6 decimal base 18 decimal quote")]
+#[test_case("2000", 18, 6 => Ok(Decimal::from_str("0.000000002").unwrap()); "18 decimal base 6 decimal quote")]
+#[test_case("0.5", 6, 8 => Ok(Decimal::from_str("50").unwrap()); "6 decimal base 8 decimal quote")]
+#[test_case("0.000001", 18, 0 => Err(ContractError::InvalidPrice); "price below decimal precision")]
+#[test_case("1", 0, 40 => Err(ContractError::TooManyDecimals); "exponent overflow")]
+fn test_to_base_unit_price(
+    price: &str,
+    base_decimals: u32,
+    quote_decimals: u32,
+) -> Result<Decimal, ContractError> {
+    to_base_unit_price(
+        Decimal::from_str(price).unwrap(),
+        base_decimals,
+        quote_decimals,
+    )
+}
+
+#[test_case("500000000000", 6, 18 => Ok(Decimal::from_str("0.5").unwrap()); "6 decimal base 18 decimal quote")]
+#[test_case("0.000000002", 18, 6 => Ok(Decimal::from_str("2000").unwrap()); "18 decimal base 6 decimal quote")]
+#[test_case("0.5", 6, 6 => Ok(Decimal::from_str("0.5").unwrap()); "equal exponents")]
+fn test_from_base_unit_price(
+    price: &str,
+    base_decimals: u32,
+    quote_decimals: u32,
+) -> Result<Decimal, ContractError> {
+    from_base_unit_price(
+        Decimal::from_str(price).unwrap(),
+        base_decimals,
+        quote_decimals,
+    )
+}
+
+#[test_case("0.5", 0 => Ok(Decimal::from_str("0.5").unwrap()); "no slippage")]
+#[test_case("0.5", 10 => Ok(Decimal::from_str("0.5005").unwrap()); "10 basis points")]
+#[test_case("2", 10000 => Ok(Decimal::from_str("4").unwrap()); "100 percent")]
+fn test_apply_slippage(price: &str, slippage_basis_points: u128) -> Result<Decimal, ContractError> {
+    apply_slippage(Decimal::from_str(price).unwrap(), slippage_basis_points)
+}
+
+#[test_case("untrn" => Ok(DenomFormat::Native); "native denom")]
+#[test_case("uibcusdc" => Ok(DenomFormat::Native); "native denom with ibc in name")]
+#[test_case("ibc/B559A80D62249C8AA07A380E2A2BEA6E5CA9A6F079C912C3A9E9B494105E4F81" => Ok(DenomFormat::Ibc { hash: "B559A80D62249C8AA07A380E2A2BEA6E5CA9A6F079C912C3A9E9B494105E4F81".to_string() }); "ibc denom")]
+#[test_case("factory/neutron1creator/ustable" => Ok(DenomFormat::Factory { creator: "neutron1creator".to_string(), subdenom: "ustable".to_string() }); "tokenfactory denom")]
+#[test_case("factory/neutron1creator/sub/denom" => Ok(DenomFormat::Factory { creator: "neutron1creator".to_string(), subdenom: "sub/denom".to_string() }); "tokenfactory subdenom with slash")]
+fn test_validate_denom_format(denom: &str) -> Result<DenomFormat, ContractError> {
+    validate_denom_format(denom)
+}
+
+#[test_case("ibc/B559A80D62249C8AA07A380E2A2BEA6E5CA9A6F079C912C3A9E9B494105E4F8"; "ibc denom too short")]
+#[test_case("ibc/b559a80d62249c8aa07a380e2a2bea6e5ca9a6f079c912c3a9e9b494105e4f81"; "ibc denom lowercase hash")]
+#[test_case("factory/neutron1creator"; "tokenfactory denom without subdenom")]
+#[test_case("factory//ustable"; "tokenfactory denom without creator")]
+#[test_case("factory/neutron1creator/this_subdenom_is_way_too_long_for_tokenfactory"; "tokenfactory subdenom too long")]
+#[test_case("un"; "native denom too short")]
+#[test_case("1untrn"; "native denom starting with a digit")]
+#[test_case("u ntrn"; "native denom with whitespace")]
+fn test_validate_denom_format_error(denom: &str) {
+    assert!(validate_denom_format(denom).is_err());
+}
+
+fn route(denoms: &[&str]) -> Vec<String> {
+    denoms.iter().map(|denom| denom.to_string()).collect()
+}
+
+#[test_case(&[("uusdc", "untrn")], "uusdc", "untrn" => Ok(route(&["uusdc", "untrn"])); "direct pair")]
+#[test_case(&[("uusdc", "untrn"), ("untrn", "uatom")], "uusdc", "uatom" => Ok(route(&["uusdc", "untrn", "uatom"])); "one intermediate denom")]
+#[test_case(&[("untrn", "uusdc"), ("uatom", "untrn"), ("uatom", "uusdc")], "uusdc", "uatom" => Ok(route(&["uusdc", "uatom"])); "shortest route wins")]
+#[test_case(&[("uusdc", "a"), ("a", "b"), ("b", "c"), ("c", "uatom")], "uusdc", "uatom" => Err(ContractError::RouteNotFound { from: "uusdc".to_string(), to: "uatom".to_string() }); "route too long")]
+#[test_case(&[("uusdc", "untrn")], "uusdc", "uatom" => Err(ContractError::RouteNotFound { from: "uusdc".to_string(), to: "uatom".to_string() }); "no route")]
+fn test_discover_route(
+    pairs: &[(&str, &str)],
+    from: &str,
+    to: &str,
+) -> Result<Vec<String>, ContractError> {
+    let mut storage = MockStorage::new();
+    for (denom_a, denom_b) in pairs {
+        DEX_PAIRS
+            .save(
+                &mut storage,
+                &format!("{}<>{}", denom_a, denom_b),
+                &(denom_a.to_string(), denom_b.to_string()),
+            )
+            .unwrap();
+    }
+    discover_route(&storage, from, to)
+}
+
+#[test_case(&["uusdc", "untrn", "uatom"] => true; "valid route")]
+#[test_case(&["uusdc"] => false; "single denom")]
+#[test_case(&["untrn", "uatom"] => false; "wrong entry denom")]
+#[test_case(&["uusdc", "untrn"] => false; "wrong exit denom")]
+#[test_case(&["uusdc", "untrn", "uusdc", "uatom"] => false; "repeated denom")]
+fn test_validate_route(hops: &[&str]) -> bool {
+    validate_route(&route(hops), "uusdc", "uatom").is_ok()
+}
+
+#[test_case("2", 0, 6, 6 => Ok(Decimal::from_str("0.5").unwrap()); "no slippage")]
+#[test_case("4", 10000, 6, 6 => Ok(Decimal::from_str("0.125").unwrap()); "100 percent slippage")]
+#[test_case("10", 0, 6, 18 => Ok(Decimal::from_str("100000000000").unwrap()); "18 decimal target")]
+fn test_exit_limit_price(
+    target_price: &str,
+    slippage_basis_points: u128,
+    usd_decimals: u32,
+    target_decimals: u32,
+) -> Result<Decimal, ContractError> {
+    exit_limit_price(
+        Decimal::from_str(target_price).unwrap(),
+        slippage_basis_points,
+        usd_decimals,
+        target_decimals,
+    )
+}
+
+fn amounts(amounts: &[u128]) -> Vec<Uint128> {
+    amounts.iter().map(|amount| Uint128::new(*amount)).collect()
+}
+
+#[test_case(1000, &[100, 300] => amounts(&[250, 750]); "proportional to depth")]
+#[test_case(1000, &[5, 1, 1] => amounts(&[716, 142, 142]); "remainder to the deepest venue")]
+#[test_case(1000, &[1, 1, 1] => amounts(&[334, 333, 333]); "ties go to the first venue")]
+#[test_case(1000, &[0, 0] => amounts(&[1000, 0]); "no depth")]
+#[test_case(1000, &[0, 50] => amounts(&[0, 1000]); "one venue without depth")]
+#[test_case(1000, &[] => amounts(&[]); "no venues")]
+fn test_split_by_depth(amount: u128, depths: &[u128]) -> Vec<Uint128> {
+    split_by_depth(Uint128::new(amount), &amounts(depths)).unwrap()
+}
+
+#[test_case(1000, 1000, "0.8", false => Some(Uint128::new(1000)); "full fill")]
+#[test_case(800, 1000, "0.8", false => Some(Uint128::new(1000)); "fill at the min ratio")]
+#[test_case(799, 1000, "0.8", false => None; "fill below the min ratio is skipped")]
+#[test_case(500, 1000, "0.8", true => Some(Uint128::new(500)); "fill below the min ratio is downsized")]
+#[test_case(0, 1000, "0.8", true => None; "no fill is skipped")]
+#[test_case(1, 3, "0.5", false => None; "min fill rounds up")]
+fn test_checked_fill_amount(
+    expected_fill: u128,
+    amount: u128,
+    min_fill_ratio: &str,
+    downsize: bool,
+) -> Option<Uint128> {
+    checked_fill_amount(
+        Uint128::new(expected_fill),
+        Uint128::new(amount),
+        &LiquidityCheck {
+            min_fill_ratio: Decimal::from_str(min_fill_ratio).unwrap(),
+            downsize,
+        },
+    )
+}
+
+#[test_case(100, 1000, 800 => Uint128::new(100); "total below the cap")]
+#[test_case(100, 1000, 1000 => Uint128::new(100); "total at the cap")]
+#[test_case(100, 500, 1000 => Uint128::new(50); "total above the cap")]
+#[test_case(333, 100, 1000 => Uint128::new(33); "rounds down")]
+#[test_case(100, 0, 1000 => Uint128::zero(); "zero cap")]
+fn test_pro_rata(amount: u128, cap: u128, total: u128) -> Uint128 {
+    pro_rata(Uint128::new(amount), Uint128::new(cap), Uint128::new(total))
+}
+
+#[test_case(0; "empty")]
+#[test_case(1; "single schedule")]
+#[test_case(10; "many schedules")]
+fn test_shuffled_order_is_a_permutation(len: usize) {
+    for height in 0..100 {
+        let mut order = shuffled_order(len, run_seed(height, 7));
+        order.sort();
+        assert_eq!(order, (0..len).collect::<Vec<usize>>());
+    }
+}
+
+#[test]
+fn test_shuffled_order_is_deterministic() {
+    assert_eq!(
+        shuffled_order(10, run_seed(100, 7)),
+        shuffled_order(10, run_seed(100, 7))
+    );
+    assert_ne!(
+        shuffled_order(10, run_seed(100, 7)),
+        shuffled_order(10, run_seed(101, 7))
+    );
+}
+
+#[test_case(2, 0; "two schedules")]
+#[test_case(5, 3; "five schedules")]
+#[test_case(8, 1 << 70; "eight schedules with a large nonce")]
+fn test_shuffled_order_positions_are_evenly_distributed(len: usize, nonce: u128) {
+    let runs = 10000u64;
+    // counts[schedule][position]
+    let mut counts = vec![vec![0u64; len]; len];
+    for height in 0..runs {
+        for (position, schedule) in shuffled_order(len, run_seed(height, nonce))
+            .into_iter()
+            .enumerate()
+        {
+            counts[schedule][position] += 1;
+        }
+    }
+
+    // every schedule is at every position about runs / len times
+    let expected = runs / len as u64;
+    for positions in counts.iter() {
+        for count in positions.iter() {
+            assert!(
+                count.abs_diff(expected) * 10 < expected,
+                "count {} too far from {}: {:?}",
+                count,
+                expected,
+                counts
+            );
+        }
+    }
+}
+
+#[test_case(1000, &[100, 300] => (amounts(&[250, 750]), Uint128::zero()); "divides evenly")]
+#[test_case(1000, &[1, 1, 1] => (amounts(&[333, 333, 333]), Uint128::new(1)); "dust is left over")]
+#[test_case(10, &[7, 5, 3] => (amounts(&[4, 3, 2]), Uint128::new(1)); "rounds every share down")]
+#[test_case(1000, &[0, 0] => (amounts(&[0, 0]), Uint128::new(1000)); "no weight")]
+fn test_split_pro_rata(total: u128, weights: &[u128]) -> (Vec<Uint128>, Uint128) {
+    split_pro_rata(Uint128::new(total), &amounts(weights)).unwrap()
+}
+
+#[test_case(1000, &[100, 300, 600] => Ok(amounts(&[100, 300, 600])); "full fill")]
+#[test_case(1000, &[1000, 1000, 1000] => Ok(amounts(&[334, 333, 333])); "remainder to the first share")]
+#[test_case(3, &[1, 1, 5] => Ok(amounts(&[1, 0, 2])); "remainder to the first share below its weight")]
+#[test_case(0, &[10, 20] => Ok(amounts(&[0, 0])); "no fill")]
+#[test_case(31, &[10, 20] => Err(ContractError::InsufficientLiquidity { requested: Uint128::new(30), available: Uint128::new(31) }); "total above the weights")]
+fn test_split_exact(total: u128, weights: &[u128]) -> Result<Vec<Uint128>, ContractError> {
+    split_exact(Uint128::new(total), &amounts(weights))
+}
+
+fn netting(
+    matched_usd: u128,
+    matched_ntrn: u128,
+    buy_debits: &[u128],
+    sell_debits: &[u128],
+    buy_credits: &[u128],
+    sell_credits: &[u128],
+) -> Netting {
+    Netting {
+        matched_usd: Uint128::new(matched_usd),
+        matched_ntrn: Uint128::new(matched_ntrn),
+        buy_debits: amounts(buy_debits),
+        sell_debits: amounts(sell_debits),
+        buy_credits: amounts(buy_credits),
+        sell_credits: amounts(sell_credits),
+    }
+}
+
+#[test_case(&[100, 300], &[50], "2" => netting(100, 50, &[25, 75], &[50], &[13, 37], &[100]); "buys larger than sells")]
+#[test_case(&[100], &[30, 70, 100], "0.5" => netting(100, 200, &[100], &[30, 70, 100], &[200], &[15, 35, 50]); "sells larger than buys")]
+#[test_case(&[10], &[7], "3" => netting(10, 3, &[10], &[3], &[3], &[10]); "denom_ntrn rounds down")]
+#[test_case(&[100], &[], "2" => netting(0, 0, &[0], &[], &[0], &[]); "no sells")]
+#[test_case(&[1], &[100], "2" => netting(0, 0, &[0], &[0], &[0], &[0]); "less than one unit")]
+fn test_net_intents(buys: &[u128], sells: &[u128], price: &str) -> Netting {
+    net_intents(
+        &amounts(buys),
+        &amounts(sells),
+        Decimal::from_str(price).unwrap(),
+    )
+    .unwrap()
+}
+
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[] => true; "no ladder")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[10, 25, 50] => true; "increasing steps")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[10, 100] => true; "last step at max slippage")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[10, 101] => false; "step above max slippage")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[25, 10] => false; "decreasing steps")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[10, 10] => false; "repeated step")]
+#[test_case(ScheduleOrderType::FillOrKill, &[10, 25] => false; "fill or kill")]
+#[test_case(ScheduleOrderType::JustInTime, &[10, 25] => false; "maker order")]
+fn test_validate_slippage_ladder(order_type: ScheduleOrderType, slippage_ladder: &[u128]) -> bool {
+    validate_order_config(&order_type, 100, None, slippage_ladder, &[]).is_ok()
+}
+
+fn tranche_ladder(steps: &[(&str, u128)]) -> Vec<TrancheStep> {
+    steps
+        .iter()
+        .map(|(fraction, slippage_basis_points)| TrancheStep {
+            fraction: Decimal::from_str(fraction).unwrap(),
+            slippage_basis_points: *slippage_basis_points,
+        })
+        .collect()
+}
+
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[], &[("0.4", 0), ("0.3", 10), ("0.3", 25)] => true; "increasing prices")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[], &[("1", 100)] => true; "one step at max slippage")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[], &[("0.5", 0), ("0.5", 101)] => false; "step above max slippage")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[], &[("0.5", 10), ("0.5", 10)] => false; "repeated price")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[], &[("0.4", 0), ("0.4", 10)] => false; "fractions below 1")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[], &[("0", 0), ("1", 10)] => false; "empty step")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, &[10], &[("1", 10)] => false; "with a slippage ladder")]
+#[test_case(ScheduleOrderType::FillOrKill, &[], &[("1", 10)] => false; "fill or kill")]
+fn test_validate_tranche_ladder(
+    order_type: ScheduleOrderType,
+    slippage_ladder: &[u128],
+    steps: &[(&str, u128)],
+) -> bool {
+    validate_order_config(
+        &order_type,
+        100,
+        None,
+        slippage_ladder,
+        &tranche_ladder(steps),
+    )
+    .is_ok()
+}
+
+#[test_case(1000, &[4, 3, 3] => amounts(&[400, 300, 300]); "divides evenly")]
+#[test_case(10, &[1, 1, 1] => amounts(&[4, 3, 3]); "leftover to the first share")]
+#[test_case(10, &[0, 1, 1] => amounts(&[0, 5, 5]); "leftover skips shares without weight")]
+#[test_case(7, &[0, 0] => amounts(&[0, 0]); "no weight")]
+fn test_split_all(total: u128, weights: &[u128]) -> Vec<Uint128> {
+    split_all(Uint128::new(total), &amounts(weights)).unwrap()
+}
+
+#[test_case("Trade cannot be filled at the specified LimitPrice" => OrderFailure::PriceLimit; "dex limit price")]
+#[test_case("Operation exceeds max spread limit" => OrderFailure::PriceLimit; "astroport max spread")]
+#[test_case("Fill Or Kill limit order couldn't be executed in its entirety" => OrderFailure::NoLiquidity; "fill or kill")]
+#[test_case("No tradable liquidity available" => OrderFailure::NoLiquidity; "no liquidity")]
+#[test_case("0untrn is smaller than 100untrn: insufficient funds" => OrderFailure::InsufficientFunds; "insufficient funds")]
+#[test_case("out of gas" => OrderFailure::Other; "other")]
+fn test_classify_order_error(err: &str) -> OrderFailure {
+    classify_order_error(err)
+}
+
+fn schedule(id: u128, remaining_amount: u128, side: ScheduleSide, open_tranche: bool) -> Schedule {
+    Schedule {
+        remaining_amount: Uint128::new(remaining_amount),
+        owner: Addr::unchecked(format!("owner{}", id)),
+        max_sell_amount: Uint128::new(1000),
+        max_slippage_basis_points: 100,
+        id,
+        order_type: ScheduleOrderType::ImmediateOrCancel,
+        max_average_slippage_basis_points: None,
+        open_tranches: match open_tranche {
+            true => vec![OpenTranche {
+                tranche_key: "key".to_string(),
+                amount: Uint128::new(100),
+                expiration: 0,
+                withdrawn: false,
+            }],
+            false => vec![],
+        },
+        target_denom: "untrn".to_string(),
+        side,
+        slippage_ladder: vec![],
+        tranche_ladder: vec![],
+        consecutive_failures: 0,
+        parked: false,
+    }
+}
+
+fn refund(owner: &str, denom: &str, amount: u128) -> CosmosMsg {
+    BankMsg::Send {
+        to_address: owner.to_string(),
+        amount: vec![Coin::new(amount, denom)],
+    }
+    .into()
+}
+
+#[test_case(100, vec![schedule(1, 99, ScheduleSide::Buy, false)] => (vec![], vec![refund("owner1", "uusdc", 99)]); "buy below the min")]
+#[test_case(100, vec![schedule(1, 99, ScheduleSide::Sell, false)] => (vec![], vec![refund("owner1", "untrn", 99)]); "sell below the min")]
+#[test_case(100, vec![schedule(1, 100, ScheduleSide::Buy, false)] => (vec![1], vec![]); "at the min")]
+#[test_case(100, vec![schedule(1, 99, ScheduleSide::Buy, true)] => (vec![1], vec![]); "open tranche")]
+#[test_case(100, vec![schedule(1, 0, ScheduleSide::Buy, false)] => (vec![1], vec![]); "nothing to refund")]
+#[test_case(0, vec![schedule(1, 1, ScheduleSide::Buy, false)] => (vec![1], vec![]); "disabled")]
+#[test_case(100, vec![schedule(1, 50, ScheduleSide::Buy, false), schedule(2, 500, ScheduleSide::Buy, false)] => (vec![2], vec![refund("owner1", "uusdc", 50)]); "keeps tradable schedules")]
+fn test_close_dust_schedules(
+    min_tradable_amount: u128,
+    schedules: Vec<Schedule>,
+) -> (Vec<u128>, Vec<CosmosMsg>) {
+    let mut config = mock_config();
+    config.min_tradable_amounts = vec![
+        Coin::new(min_tradable_amount, "uusdc"),
+        Coin::new(min_tradable_amount, "untrn"),
+    ];
+    let mut schedules = Schedules {
+        schedules,
+        nonce: 3,
+    };
+    let refunds = close_dust_schedules(&config, &mut schedules, None);
+    (
+        schedules
+            .schedules
+            .iter()
+            .map(|schedule| schedule.id)
+            .collect(),
+        refunds,
+    )
+}
+
+// each schedule is measured against the amount of the denom it sells
+#[test]
+fn test_close_dust_schedules_per_denom() {
+    let mut config = mock_config();
+    config.min_tradable_amounts = vec![Coin::new(100u128, "uusdc"), Coin::new(1000u128, "untrn")];
+    let mut schedules = Schedules {
+        schedules: vec![
+            schedule(1, 500, ScheduleSide::Buy, false),
+            schedule(2, 500, ScheduleSide::Sell, false),
+        ],
+        nonce: 3,
+    };
+    let refunds = close_dust_schedules(&config, &mut schedules, None);
+    assert_eq!(refunds, vec![refund("owner2", "untrn", 500)]);
+    assert_eq!(schedules.schedules.len(), 1);
+    assert_eq!(schedules.schedules[0].id, 1);
+}
+
+// replies only close the schedules they settled
+#[test]
+fn test_close_dust_schedules_only_given() {
+    let mut config = mock_config();
+    config.min_tradable_amounts = vec![Coin::new(100u128, "uusdc")];
+    let mut schedules = Schedules {
+        schedules: vec![
+            schedule(1, 50, ScheduleSide::Buy, false),
+            schedule(2, 60, ScheduleSide::Buy, false),
+        ],
+        nonce: 3,
+    };
+    let refunds = close_dust_schedules(&config, &mut schedules, Some(&[2]));
+    assert_eq!(refunds, vec![refund("owner2", "uusdc", 60)]);
+    assert_eq!(schedules.schedules[0].id, 1);
+    assert_eq!(schedules.schedules.len(), 1);
+}
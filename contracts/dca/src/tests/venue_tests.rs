@@ -0,0 +1,315 @@
+use crate::contract_tests::decode_msg;
+use crate::error::ContractError;
+use crate::execute::to_dex_price;
+use crate::msg::{
+    AstroportAsset, AstroportAssetInfo, AstroportExecuteMsg, AstroportQueryMsg,
+    AstroportSimulationResponse,
+};
+use crate::state::ScheduleOrderType;
+use crate::utils::from_base_unit_price;
+use crate::venue::{Astroport, ExecutionVenue, NeutronDex, SwapRequest, SwapResult};
+use cosmwasm_std::testing::{mock_dependencies, mock_env};
+use cosmwasm_std::{
+    from_json, to_json_binary, Binary, Coin, ContractResult as CwContractResult, CosmosMsg,
+    Decimal, Event, MsgResponse, SubMsgResponse, SystemResult, Uint128, WasmMsg, WasmQuery,
+};
+use neutron_std::shim::Timestamp;
+use neutron_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use neutron_std::types::neutron::dex::{
+    LimitOrderType, MsgPlaceLimitOrder, MsgPlaceLimitOrderResponse,
+};
+use prost::Message;
+use std::str::FromStr;
+use test_case::test_case;
+
+fn request(order_type: ScheduleOrderType, max_average_price: Option<&str>) -> SwapRequest {
+    SwapRequest {
+        receiver: "owner".to_string(),
+        token_in: "uusdc".to_string(),
+        token_out: "untrn".to_string(),
+        amount_in: Uint128::new(1000),
+        limit_price: Decimal::from_str("0.5").unwrap(),
+        max_average_price: max_average_price.map(|price| Decimal::from_str(price).unwrap()),
+        order_type,
+        decimals_in: 6,
+        decimals_out: 6,
+    }
+}
+
+#[allow(deprecated)]
+fn reply(events: Vec<Event>, msg_responses: Vec<MsgResponse>) -> SubMsgResponse {
+    SubMsgResponse {
+        events,
+        data: None,
+        msg_responses,
+    }
+}
+
+fn proto_coin(denom: &str, amount: &str) -> Option<ProtoCoin> {
+    Some(ProtoCoin {
+        denom: denom.to_string(),
+        amount: amount.to_string(),
+    })
+}
+
+#[test_case(ScheduleOrderType::ImmediateOrCancel, LimitOrderType::ImmediateOrCancel, None, None; "immediate or cancel")]
+#[test_case(ScheduleOrderType::ImmediateOrCancel, LimitOrderType::ImmediateOrCancel, None, Some("0.4"); "average price protection")]
+#[test_case(ScheduleOrderType::FillOrKill, LimitOrderType::FillOrKill, None, None; "fill or kill")]
+#[test_case(ScheduleOrderType::GoodTilTime { expiry_seconds: 60 }, LimitOrderType::GoodTilTime, Some(60), None; "good til time")]
+#[test_case(ScheduleOrderType::JustInTime, LimitOrderType::JustInTime, None, None; "just in time")]
+fn test_neutron_dex_swap_msg(
+    order_type: ScheduleOrderType,
+    limit_order_type: LimitOrderType,
+    expiry_seconds: Option<i64>,
+    max_average_price: Option<&str>,
+) {
+    let env = mock_env();
+    let now = env.block.time.seconds() as i64;
+    // maker tranches are owned by the contract so it can withdraw and cancel them
+    let receiver = match order_type.is_maker() {
+        true => env.contract.address.to_string(),
+        false => "owner".to_string(),
+    };
+    let expected: CosmosMsg = MsgPlaceLimitOrder {
+        creator: env.contract.address.to_string(),
+        receiver,
+        token_in: "uusdc".to_string(),
+        token_out: "untrn".to_string(),
+        tick_index_in_to_out: 0,
+        amount_in: "1000".to_string(),
+        order_type: limit_order_type.into(),
+        expiration_time: expiry_seconds.map(|expiry_seconds| Timestamp {
+            seconds: now + expiry_seconds,
+            nanos: 0,
+        }),
+        // the DEX bounds the average price from below in untrn per uusdc
+        min_average_sell_price: max_average_price
+            .map(|price| to_dex_price(Decimal::one() / Decimal::from_str(price).unwrap()).unwrap()),
+        max_amount_out: None,
+        // the limit price of 0.5 uusdc per untrn sells uusdc for at least 2 untrn each
+        limit_sell_price: Some(to_dex_price(Decimal::from_str("2").unwrap()).unwrap()),
+    }
+    .into();
+
+    assert_eq!(
+        NeutronDex
+            .swap_msg(&env, &request(order_type, max_average_price))
+            .unwrap(),
+        expected
+    );
+}
+
+fn place_order(max_average_price: &str, decimals_in: u32, decimals_out: u32) -> MsgPlaceLimitOrder {
+    let msg = NeutronDex
+        .swap_msg(
+            &mock_env(),
+            &SwapRequest {
+                decimals_in,
+                decimals_out,
+                ..request(
+                    ScheduleOrderType::ImmediateOrCancel,
+                    Some(max_average_price),
+                )
+            },
+        )
+        .unwrap();
+    decode_msg(&msg)
+}
+
+// a DEX price decoded into whole untrn per whole uusdc
+fn whole_sell_price(dex_price: String, decimals_in: u32, decimals_out: u32) -> Decimal {
+    let dex_price = Uint128::from_str(&dex_price).unwrap();
+    let base_unit_price = Decimal::from_atomics(dex_price, 27).unwrap();
+    from_base_unit_price(base_unit_price, decimals_in, decimals_out).unwrap()
+}
+
+// the min average price the DEX is given for a max average price in uusdc per untrn, in
+// whole untrn per whole uusdc
+fn min_average_sell_price(max_average_price: &str, decimals_in: u32) -> Decimal {
+    let place = place_order(max_average_price, decimals_in, 6);
+    whole_sell_price(place.min_average_sell_price.unwrap(), decimals_in, 6)
+}
+
+#[test_case("0.4", 6 => "2.5"; "same decimals")]
+#[test_case("0.5", 6 => "2"; "at the limit price")]
+#[test_case("0.4", 18 => "2.5"; "more decimals in")]
+fn test_neutron_dex_min_average_sell_price(max_average_price: &str, decimals_in: u32) -> String {
+    min_average_sell_price(max_average_price, decimals_in).to_string()
+}
+
+// tolerating a higher average price in uusdc per untrn accepts fewer untrn per uusdc
+#[test]
+fn test_neutron_dex_min_average_sell_price_direction() {
+    assert!(min_average_sell_price("0.6", 6) < min_average_sell_price("0.4", 6));
+}
+
+// both bounds are in untrn base units per uusdc base unit. With 6 decimals in and 18 out a
+// limit price of 0.5 is 2 * 10^12 and an average price of 0.4 is 2.5 * 10^12, the average
+// bound is the tighter one
+#[test]
+fn test_neutron_dex_sell_prices_share_convention() {
+    let place = place_order("0.4", 6, 18);
+    let limit_sell_price = place.limit_sell_price.unwrap();
+    let min_average_sell_price = place.min_average_sell_price.unwrap();
+    assert_eq!(limit_sell_price, format!("2{}", "0".repeat(39)));
+    assert_eq!(min_average_sell_price, format!("25{}", "0".repeat(38)));
+    assert_eq!(
+        whole_sell_price(limit_sell_price, 6, 18),
+        Decimal::from_str("2").unwrap()
+    );
+    assert_eq!(
+        whole_sell_price(min_average_sell_price, 6, 18),
+        Decimal::from_str("2.5").unwrap()
+    );
+}
+
+#[test_case("1000", "1990", "1000", "key" => SwapResult { amount_in: Uint128::new(1000), amount_out: Uint128::new(1990), placed: Uint128::new(1000), tranche_key: Some("key".to_string()) }; "full taker fill")]
+#[test_case("400", "790", "1000", "key" => SwapResult { amount_in: Uint128::new(400), amount_out: Uint128::new(790), placed: Uint128::new(1000), tranche_key: Some("key".to_string()) }; "partial fill resting as maker")]
+#[test_case("0", "0", "1000", "" => SwapResult { amount_in: Uint128::zero(), amount_out: Uint128::zero(), placed: Uint128::new(1000), tranche_key: None }; "no fill")]
+fn test_neutron_dex_decode_reply(
+    taker_in: &str,
+    taker_out: &str,
+    coin_in: &str,
+    tranche_key: &str,
+) -> SwapResult {
+    let response = MsgPlaceLimitOrderResponse {
+        tranche_key: tranche_key.to_string(),
+        coin_in: proto_coin("uusdc", coin_in),
+        taker_coin_out: proto_coin("untrn", taker_out),
+        taker_coin_in: proto_coin("uusdc", taker_in),
+    };
+    NeutronDex
+        .decode_reply(&reply(
+            vec![],
+            vec![MsgResponse {
+                type_url: "/neutron.dex.MsgPlaceLimitOrderResponse".to_string(),
+                value: Binary::from(response.encode_to_vec()),
+            }],
+        ))
+        .unwrap()
+}
+
+#[test]
+fn test_neutron_dex_decode_reply_without_response() {
+    assert_eq!(
+        NeutronDex.decode_reply(&reply(vec![], vec![])),
+        Err(ContractError::NoResponseData)
+    );
+}
+
+#[test_case(None => "0.5"; "limit price")]
+#[test_case(Some("0.4") => "0.4"; "average price below the limit")]
+#[test_case(Some("0.6") => "0.5"; "average price above the limit")]
+fn test_astroport_swap_msg(max_average_price: Option<&str>) -> String {
+    let venue = Astroport {
+        pair_address: "pair".to_string(),
+    };
+    let msg = venue
+        .swap_msg(
+            &mock_env(),
+            &request(ScheduleOrderType::ImmediateOrCancel, max_average_price),
+        )
+        .unwrap();
+
+    let CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr,
+        msg,
+        funds,
+    }) = msg
+    else {
+        panic!("expected a wasm execute message");
+    };
+    assert_eq!(contract_addr, "pair");
+    assert_eq!(funds, vec![Coin::new(1000u128, "uusdc")]);
+    let swap: AstroportExecuteMsg = from_json(&msg).unwrap();
+    let AstroportExecuteMsg::Swap {
+        offer_asset,
+        ask_asset_info,
+        belief_price,
+        max_spread,
+        to,
+    } = swap;
+    assert_eq!(
+        offer_asset,
+        AstroportAsset {
+            info: AstroportAssetInfo::NativeToken {
+                denom: "uusdc".to_string()
+            },
+            amount: Uint128::new(1000),
+        }
+    );
+    assert_eq!(
+        ask_asset_info,
+        Some(AstroportAssetInfo::NativeToken {
+            denom: "untrn".to_string()
+        })
+    );
+    assert_eq!(max_spread, Some(Decimal::zero()));
+    assert_eq!(to, Some("owner".to_string()));
+    belief_price.unwrap().to_string()
+}
+
+#[test_case(ScheduleOrderType::GoodTilTime { expiry_seconds: 60 }; "good til time")]
+#[test_case(ScheduleOrderType::JustInTime; "just in time")]
+fn test_astroport_rejects_maker_orders(order_type: ScheduleOrderType) {
+    let venue = Astroport {
+        pair_address: "pair".to_string(),
+    };
+    assert!(!venue.supports_maker_orders());
+    assert!(matches!(
+        venue.swap_msg(&mock_env(), &request(order_type, None)),
+        Err(ContractError::InvalidOrderConfig { .. })
+    ));
+}
+
+fn swap_event(contract_address: &str, offer_amount: &str, return_amount: &str) -> Event {
+    Event::new("wasm")
+        .add_attribute("_contract_address", contract_address)
+        .add_attribute("action", "swap")
+        .add_attribute("offer_amount", offer_amount)
+        .add_attribute("return_amount", return_amount)
+}
+
+#[test_case(vec![swap_event("pair", "1000", "1990")] => Ok(SwapResult { amount_in: Uint128::new(1000), amount_out: Uint128::new(1990), placed: Uint128::new(1000), tranche_key: None }); "swap event")]
+#[test_case(vec![Event::new("transfer"), swap_event("other_pair", "1", "1"), swap_event("pair", "1000", "1990")] => Ok(SwapResult { amount_in: Uint128::new(1000), amount_out: Uint128::new(1990), placed: Uint128::new(1000), tranche_key: None }); "event of the pair")]
+#[test_case(vec![swap_event("other_pair", "1000", "1990")] => Err(ContractError::NoResponseData); "no event of the pair")]
+#[test_case(vec![swap_event("pair", "1000", "invalid")] => Err(ContractError::DecodingError); "invalid amount")]
+fn test_astroport_decode_reply(events: Vec<Event>) -> Result<SwapResult, ContractError> {
+    let venue = Astroport {
+        pair_address: "pair".to_string(),
+    };
+    venue.decode_reply(&reply(events, vec![]))
+}
+
+#[test_case(2000, None => Uint128::new(1000); "returns the belief amount")]
+#[test_case(1999, None => Uint128::zero(); "returns less than the belief amount")]
+#[test_case(2400, Some("0.4") => Uint128::zero(); "returns less than the average price bound")]
+#[test_case(2500, Some("0.4") => Uint128::new(1000); "returns the average price bound")]
+fn test_astroport_simulate_fill(return_amount: u128, max_average_price: Option<&str>) -> Uint128 {
+    let mut deps = mock_dependencies();
+    deps.querier.update_wasm(move |query| match query {
+        WasmQuery::Smart { contract_addr, msg } if contract_addr == "pair" => {
+            let AstroportQueryMsg::Simulation { offer_asset, .. } = from_json(msg).unwrap();
+            assert_eq!(offer_asset.amount, Uint128::new(1000));
+            SystemResult::Ok(CwContractResult::Ok(
+                to_json_binary(&AstroportSimulationResponse {
+                    return_amount: Uint128::new(return_amount),
+                    spread_amount: Uint128::zero(),
+                    commission_amount: Uint128::zero(),
+                })
+                .unwrap(),
+            ))
+        }
+        _ => panic!("unexpected query {:?}", query),
+    });
+    let venue = Astroport {
+        pair_address: "pair".to_string(),
+    };
+    venue
+        .simulate_fill(
+            deps.as_ref(),
+            &mock_env(),
+            &request(ScheduleOrderType::ImmediateOrCancel, max_average_price),
+        )
+        .unwrap()
+}
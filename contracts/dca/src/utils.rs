@@ -1,9 +1,21 @@
+use std::collections::VecDeque;
 use std::str::FromStr;
 
 use crate::error::{ContractError, ContractResult};
-use crate::state::{Schedules, CONFIG};
-use cosmwasm_std::{Decimal, Deps, Env, Int128, Response, SubMsgResponse, Uint128};
-use neutron_std::types::neutron::dex::MsgPlaceLimitOrderResponse;
+use crate::state::{
+    Asset, Config, IbcTrace, LiquidityCheck, OraclePolicy, PairData, PricePath, RunCap, Schedule,
+    ScheduleOrderType, ScheduleSide, Schedules, TrancheStep, Venue, ASSETS, CONFIG, DEX_PAIRS,
+    ROUTES,
+};
+use cosmwasm_std::{
+    BankMsg, Coin, CosmosMsg, Decimal, Deps, Env, Int128, Order, Response, StdResult, Storage,
+    SubMsgResponse, Uint128,
+};
+use neutron_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use neutron_std::types::ibc::applications::transfer::v1::TransferQuerier;
+use neutron_std::types::neutron::dex::{
+    tick_liquidity::Liquidity, DexQuerier, MsgPlaceLimitOrderResponse,
+};
 use neutron_std::types::slinky::{
     marketmap::v1::{MarketMap, MarketResponse, MarketmapQuerier},
     oracle::v1::{GetAllCurrencyPairsResponse, GetPriceResponse, OracleQuerier},
@@ -48,21 +60,39 @@ pub fn validate_market(
     deps: &Deps,
     env: &Env,
     pair: &CurrencyPair,
-    max_blocks_old: u64,
+    policy: &OraclePolicy,
 ) -> ContractResult<Response> {
     // get price response here to avoid querying twice on recent and not_nil checks
     let price_response = query_oracle_price(deps, pair)?;
-    validate_market_supported_xoracle(deps, &pair, None)?;
-    validate_market_supported_xmarketmap(deps, &pair, None)?;
-    // validate_market_enabled(deps, &pair, None)?;
-    validate_price_recent(
-        deps,
-        env,
-        &pair,
-        max_blocks_old,
-        Some(price_response.clone()),
-    )?;
-    validate_price_not_nil(deps, &pair, Some(price_response.clone()))?;
+    validate_market_supported_xoracle(deps, pair, None)?;
+    validate_market_supported_xmarketmap(deps, pair, None)?;
+    validate_oracle_policy(deps, env, pair, policy, Some(price_response))?;
+    Ok(Response::new())
+}
+
+// Enforces every check of the oracle policy against the current price of the pair
+pub fn validate_oracle_policy(
+    deps: &Deps,
+    env: &Env,
+    pair: &CurrencyPair,
+    policy: &OraclePolicy,
+    oracle_price_response: Option<GetPriceResponse>,
+) -> ContractResult<Response> {
+    let oracle_price_response = match oracle_price_response {
+        Some(response) => response,
+        None => query_oracle_price(deps, pair)?,
+    };
+    validate_price_not_nil(deps, pair, Some(oracle_price_response.clone()))?;
+    validate_price_recent(deps, env, pair, policy, Some(oracle_price_response.clone()))?;
+
+    // only query x/marketmap if the policy requires it
+    if policy.require_enabled || policy.min_provider_count > 0 {
+        let market_response = query_marketmap_market(deps, pair)?;
+        if policy.require_enabled {
+            validate_market_enabled(deps, pair, Some(market_response.clone()))?;
+        }
+        validate_market_providers(deps, pair, policy.min_provider_count, Some(market_response))?;
+    }
     Ok(Response::new())
 }
 
@@ -70,7 +100,7 @@ pub fn validate_price_recent(
     deps: &Deps,
     env: &Env,
     pair: &CurrencyPair,
-    max_blocks_old: u64,
+    policy: &OraclePolicy,
     oracle_price_response: Option<GetPriceResponse>,
 ) -> ContractResult<Response> {
     let current_block_height: u64 = env.block.height;
@@ -85,11 +115,28 @@ pub fn validate_price_recent(
             symbol: pair.base.clone(),
             quote: pair.quote.clone(),
         })?;
-    if (current_block_height - price.block_height) > max_blocks_old {
+    if current_block_height.saturating_sub(price.block_height) > policy.max_blocks_old {
         return Err(ContractError::PriceTooOld {
             symbol: pair.base.clone(),
             quote: pair.quote.clone(),
-            max_blocks: max_blocks_old,
+            max_blocks: policy.max_blocks_old,
+        });
+    }
+
+    // a price without a timestamp cannot be proven fresh
+    let price_timestamp =
+        price
+            .block_timestamp
+            .ok_or_else(|| ContractError::PriceNotAvailable {
+                symbol: pair.base.clone(),
+                quote: pair.quote.clone(),
+            })?;
+    let price_age = (env.block.time.seconds() as i64).saturating_sub(price_timestamp.seconds);
+    if price_age > policy.max_seconds_old as i64 {
+        return Err(ContractError::PriceTooOldSeconds {
+            symbol: pair.base.clone(),
+            quote: pair.quote.clone(),
+            max_seconds: policy.max_seconds_old,
         });
     }
 
@@ -106,16 +153,47 @@ pub fn validate_market_enabled(
         None => query_marketmap_market(deps, &pair)?,
     };
 
-    if let Some(market) = marketmap_market_response.market {
-        if let Some(ticker) = market.ticker {
-            if !ticker.enabled {
-                return Err(ContractError::UnsupportedMarket {
-                    symbol: pair.base.clone(),
-                    quote: pair.quote.clone(),
-                    location: "x/marketmap".to_string(),
-                });
-            }
-        }
+    // a market without a ticker cannot be shown to be enabled
+    let ticker = marketmap_market_response
+        .market
+        .and_then(|market| market.ticker)
+        .ok_or_else(|| ContractError::UnsupportedMarket {
+            symbol: pair.base.clone(),
+            quote: pair.quote.clone(),
+            location: "x/marketmap".to_string(),
+        })?;
+    if !ticker.enabled {
+        return Err(ContractError::DisabledMarket {
+            symbol: pair.base.clone(),
+            quote: pair.quote.clone(),
+            location: "x/marketmap".to_string(),
+        });
+    }
+    Ok(Response::new())
+}
+
+pub fn validate_market_providers(
+    deps: &Deps,
+    pair: &CurrencyPair,
+    min_provider_count: u64,
+    marketmap_market_response: Option<MarketResponse>,
+) -> ContractResult<Response> {
+    let marketmap_market_response: MarketResponse = match marketmap_market_response {
+        Some(response) => response,
+        None => query_marketmap_market(deps, &pair)?,
+    };
+
+    let providers = marketmap_market_response
+        .market
+        .map(|market| market.provider_configs.len() as u64)
+        .unwrap_or_default();
+    if providers < min_provider_count {
+        return Err(ContractError::InsufficientProviders {
+            symbol: pair.base.clone(),
+            quote: pair.quote.clone(),
+            providers,
+            required: min_provider_count,
+        });
     }
     Ok(Response::new())
 }
@@ -181,21 +259,320 @@ pub fn validate_price_not_nil(
     Ok(Response::new())
 }
 
+// The quote every slinky market used for cross rates is denominated in
+pub const CROSS_QUOTE: &str = "USD";
+
+// Prices the pair directly if x/oracle has it, otherwise as a cross rate through CROSS_QUOTE
+pub fn resolve_price_path(deps: &Deps, pair: &CurrencyPair) -> ContractResult<PricePath> {
+    let oracle_currency_pairs = query_oracle_currency_pairs(deps)?;
+    if oracle_currency_pairs.contains(pair) {
+        return Ok(PricePath::Direct { pair: pair.clone() });
+    }
+
+    // a USD quoted pair missing from x/oracle can't be derived from anything else
+    if pair.base == CROSS_QUOTE || pair.quote == CROSS_QUOTE {
+        return Err(ContractError::UnsupportedMarket {
+            symbol: pair.base.clone(),
+            quote: pair.quote.clone(),
+            location: "x/oracle".to_string(),
+        });
+    }
+
+    let base = CurrencyPair {
+        base: pair.base.clone(),
+        quote: CROSS_QUOTE.to_string(),
+    };
+    let quote = CurrencyPair {
+        base: pair.quote.clone(),
+        quote: CROSS_QUOTE.to_string(),
+    };
+    validate_market_supported_xoracle(deps, &base, Some(oracle_currency_pairs.clone()))?;
+    validate_market_supported_xoracle(deps, &quote, Some(oracle_currency_pairs))?;
+    Ok(PricePath::Cross { base, quote })
+}
+
+/// The formats of denoms the contract accepts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DenomFormat {
+    // a native denom, e.g. untrn
+    Native,
+    // a tokenfactory denom: factory/{creator}/{subdenom}
+    Factory { creator: String, subdenom: String },
+    // an IBC voucher: ibc/{hash}
+    Ibc { hash: String },
+}
+
+// max length of a tokenfactory subdenom
+const MAX_SUBDENOM_LENGTH: usize = 44;
+
+// Checks the denom is a well formed native, tokenfactory or IBC denom.
+// Only the format is checked, use validate_denom to also check creator and IBC trace
+pub fn validate_denom_format(denom: &str) -> ContractResult<DenomFormat> {
+    let invalid_denom = |reason: &str| ContractError::InvalidDenom {
+        denom: String::from(denom),
+        reason: reason.to_string(),
+    };
+    // if it's an IBC denom
+    if let Some(hash) = denom.strip_prefix("ibc/") {
+        let invalid_ibc_denom = |reason: &str| ContractError::InvalidIbcDenom {
+            denom: String::from(denom),
+            reason: reason.to_string(),
+        };
+        // Step 1: Validate length
+        if denom.len() != 68 {
+            return Err(invalid_ibc_denom("expected length of 68 chars"));
+        }
+
+        // Step 2: Validate hash
+        if !hash
+            .chars()
+            // c.is_ascii_hexdigit() could have been used here, but it allows lowercase characters
+            .all(|c| matches!(c, '0'..='9' | 'A'..='F'))
+        {
+            return Err(invalid_ibc_denom("invalid denom hash"));
+        }
+        return Ok(DenomFormat::Ibc {
+            hash: hash.to_string(),
+        });
+    }
+
+    // sdk denom rules: [a-zA-Z][a-zA-Z0-9/:._-]{2,127}
+    if denom.len() < 3 || denom.len() > 128 {
+        return Err(invalid_denom("expected length of 3-128 chars"));
+    }
+    if !denom.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(invalid_denom("must start with a letter"));
+    }
+    if !denom
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | ':' | '.' | '_' | '-'))
+    {
+        return Err(invalid_denom("contains invalid characters"));
+    }
+
+    // if it's a tokenfactory denom
+    if let Some(rest) = denom.strip_prefix("factory/") {
+        let (creator, subdenom) = rest
+            .split_once('/')
+            .ok_or_else(|| invalid_denom("expected factory/{creator}/{subdenom}"))?;
+        if creator.is_empty() {
+            return Err(invalid_denom("empty tokenfactory creator"));
+        }
+        if subdenom.is_empty() || subdenom.len() > MAX_SUBDENOM_LENGTH {
+            return Err(invalid_denom("subdenom must be 1-44 chars"));
+        }
+        return Ok(DenomFormat::Factory {
+            creator: creator.to_string(),
+            subdenom: subdenom.to_string(),
+        });
+    }
+    Ok(DenomFormat::Native)
+}
+
+// Fully validates a denom: its format, the creator of tokenfactory denoms and, for IBC
+// denoms, that the on-chain denom trace matches the expected path and base denom
+pub fn validate_denom(
+    deps: &Deps,
+    denom: &str,
+    expected_trace: Option<&IbcTrace>,
+) -> ContractResult<Response> {
+    match validate_denom_format(denom)? {
+        DenomFormat::Native => {}
+        DenomFormat::Factory { creator, .. } => {
+            deps.api
+                .addr_validate(&creator)
+                .map_err(|_| ContractError::InvalidDenom {
+                    denom: denom.to_string(),
+                    reason: "tokenfactory creator is not a valid address".to_string(),
+                })?;
+        }
+        DenomFormat::Ibc { hash } => {
+            let expected = expected_trace.ok_or_else(|| ContractError::InvalidIbcDenom {
+                denom: denom.to_string(),
+                reason: "expected IBC trace must be configured".to_string(),
+            })?;
+            let trace = TransferQuerier::new(&deps.querier)
+                .denom_trace(hash)?
+                .denom_trace
+                .ok_or_else(|| ContractError::InvalidIbcDenom {
+                    denom: denom.to_string(),
+                    reason: "denom trace not found".to_string(),
+                })?;
+            if trace.path != expected.path || trace.base_denom != expected.base_denom {
+                return Err(ContractError::IbcTraceMismatch {
+                    denom: denom.to_string(),
+                    expected: format!("{}/{}", expected.path, expected.base_denom),
+                    actual: format!("{}/{}", trace.path, trace.base_denom),
+                });
+            }
+        }
+    }
+    Ok(Response::new())
+}
+
+// Validates a routed or paired denom against the trace of its registered asset, if any
+pub fn validate_registered_denom(deps: &Deps, denom: &str) -> ContractResult<Response> {
+    let asset = ASSETS.may_load(deps.storage, denom)?;
+    validate_denom(
+        deps,
+        denom,
+        asset.and_then(|asset| asset.ibc_trace).as_ref(),
+    )
+}
+
+// Validates a registry entry's denom and its market in x/oracle and x/marketmap.
+// Assets with the CROSS_QUOTE ticker are the quote itself and have no market
+pub fn validate_asset(deps: &Deps, asset: &Asset) -> ContractResult<Response> {
+    validate_denom(deps, &asset.denom, asset.ibc_trace.as_ref())?;
+    if asset.ticker == CROSS_QUOTE {
+        return Ok(Response::new());
+    }
+    let pair = CurrencyPair {
+        base: asset.ticker.clone(),
+        quote: CROSS_QUOTE.to_string(),
+    };
+    validate_market_supported_xoracle(deps, &pair, None)?;
+    validate_market_supported_xmarketmap(deps, &pair, None)?;
+    Ok(Response::new())
+}
+
+pub fn load_enabled_asset(storage: &dyn Storage, denom: &str) -> ContractResult<Asset> {
+    let asset =
+        ASSETS
+            .may_load(storage, denom)?
+            .ok_or_else(|| ContractError::AssetNotRegistered {
+                denom: denom.to_string(),
+            })?;
+    if !asset.enabled {
+        return Err(ContractError::AssetDisabled {
+            denom: denom.to_string(),
+        });
+    }
+    Ok(asset)
+}
+
+// Constructs the slinky pair of two denoms from their registered tickers
+pub fn pair_from_registry(
+    storage: &dyn Storage,
+    base_denom: &str,
+    quote_denom: &str,
+) -> ContractResult<CurrencyPair> {
+    let base = load_enabled_asset(storage, base_denom)?;
+    let quote = load_enabled_asset(storage, quote_denom)?;
+    if base.ticker == quote.ticker {
+        return Err(ContractError::InvalidCurrencyPair {
+            base: base.ticker,
+            quote: quote.ticker,
+            reason: "base and quote must differ".to_string(),
+        });
+    }
+    Ok(CurrencyPair {
+        base: base.ticker,
+        quote: quote.ticker,
+    })
+}
+
+// Builds the pair data of denom_ntrn/denom_usd from the registry and validates every
+// x/oracle market its price is read from
+pub fn build_pair_data(
+    deps: &Deps,
+    env: &Env,
+    denom_ntrn: &str,
+    denom_usd: &str,
+    policy: &OraclePolicy,
+) -> ContractResult<PairData> {
+    let currency_pair = pair_from_registry(deps.storage, denom_ntrn, denom_usd)?;
+
+    // price the pair directly if slinky has it, otherwise through both legs' USD prices
+    let price_path = resolve_price_path(deps, &currency_pair)?;
+    for leg in price_path.legs() {
+        validate_market(deps, env, leg, policy)?;
+    }
+
+    Ok(PairData {
+        denom_ntrn: denom_ntrn.to_string(),
+        denom_usd: denom_usd.to_string(),
+        currency_pair,
+        pair_id: get_pair_id_str(denom_ntrn, denom_usd),
+        price_path,
+    })
+}
+
+// The slinky pair pricing a stablecoin in USD, built from its registered ticker
+pub fn stable_currency_pair(storage: &dyn Storage, denom: &str) -> ContractResult<CurrencyPair> {
+    let asset = load_enabled_asset(storage, denom)?;
+    if asset.ticker == CROSS_QUOTE {
+        return Err(ContractError::InvalidCurrencyPair {
+            base: asset.ticker,
+            quote: CROSS_QUOTE.to_string(),
+            reason: "stablecoin must be registered under its own ticker".to_string(),
+        });
+    }
+    Ok(CurrencyPair {
+        base: asset.ticker,
+        quote: CROSS_QUOTE.to_string(),
+    })
+}
+
+// Get price of denom_usd in USD
+pub fn get_stable_price(deps: Deps, env: &Env, config: &Config) -> ContractResult<Decimal> {
+    let pair = stable_currency_pair(deps.storage, &config.pair_data.denom_usd)?;
+    get_pair_price(deps, env, &pair, &config.oracle_policy)
+}
+
 // Get price of NTRN in USD
 pub fn get_price(deps: Deps, env: Env) -> ContractResult<Decimal> {
     let config = CONFIG.load(deps.storage)?;
-    let pair: CurrencyPair = config.pair_data.currency_pair;
+    load_enabled_asset(deps.storage, &config.pair_data.denom_ntrn)?;
+    load_enabled_asset(deps.storage, &config.pair_data.denom_usd)?;
+    get_path_price(
+        deps,
+        &env,
+        &config.pair_data.price_path,
+        &config.oracle_policy,
+    )
+}
+
+// Get price of any registered denom in denom_usd, priced through the registry
+pub fn get_denom_price(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    denom: &str,
+) -> ContractResult<Decimal> {
+    if denom == config.pair_data.denom_ntrn {
+        return get_price(deps, env.clone());
+    }
+    let pair = pair_from_registry(deps.storage, denom, &config.pair_data.denom_usd)?;
+    let path = resolve_price_path(&deps, &pair)?;
+    get_path_price(deps, env, &path, &config.oracle_policy)
+}
 
+pub fn get_path_price(
+    deps: Deps,
+    env: &Env,
+    path: &PricePath,
+    policy: &OraclePolicy,
+) -> ContractResult<Decimal> {
+    match path {
+        PricePath::Direct { pair } => get_pair_price(deps, env, pair, policy),
+        PricePath::Cross { base, quote } => cross_price(
+            get_pair_price(deps, env, base, policy)?,
+            get_pair_price(deps, env, quote, policy)?,
+        ),
+    }
+}
+
+// Get the normalized price of a single x/oracle pair, validated against the oracle policy
+pub fn get_pair_price(
+    deps: Deps,
+    env: &Env,
+    pair: &CurrencyPair,
+    policy: &OraclePolicy,
+) -> ContractResult<Decimal> {
     // Query the oracle for the price
-    let price_response: GetPriceResponse = query_oracle_price(&deps, &pair)?;
-    validate_price_not_nil(&deps, &pair, Some(price_response.clone()))?;
-    validate_price_recent(
-        &deps,
-        &env,
-        &pair,
-        config.max_blocks_old,
-        Some(price_response.clone()),
-    )?;
+    let price_response: GetPriceResponse = query_oracle_price(&deps, pair)?;
+    validate_oracle_policy(&deps, env, pair, policy, Some(price_response.clone()))?;
 
     // Parse the price string to Int128 and normalize
     let price_int128 = Int128::from_str(&price_response.price.unwrap().price)
@@ -205,6 +582,445 @@ pub fn get_price(deps: Deps, env: Env) -> ContractResult<Decimal> {
     Ok(price)
 }
 
+// Price of base in quote from the prices of both in a common quote
+pub fn cross_price(base_price: Decimal, quote_price: Decimal) -> ContractResult<Decimal> {
+    if quote_price.is_zero() {
+        return Err(ContractError::InvalidPrice);
+    }
+    base_price
+        .checked_div(quote_price)
+        .map_err(|_| ContractError::DecimalDivisionError)
+}
+
+// Get price of NTRN in USD from the top of book of the DEX pool.
+// Only NTRN liquidity that can be bought with USD is considered.
+pub fn get_dex_price(
+    deps: Deps,
+    config: &Config,
+    min_liquidity: Uint128,
+) -> ContractResult<Decimal> {
+    let mut depth = Uint128::zero();
+    let mut best_price: Option<Decimal> = None;
+    for (reserves, price) in query_book(
+        deps,
+        &config.pair_data.denom_usd,
+        &config.pair_data.denom_ntrn,
+    )? {
+        // price_taker_to_maker is the amount of USD paid per NTRN
+        depth = depth.checked_add(reserves)?;
+        best_price = Some(best_price.map_or(price, |best| best.min(price)));
+    }
+
+    let price = best_price.ok_or(ContractError::LiquidityNotFound)?;
+    if depth < min_liquidity {
+        return Err(ContractError::InsufficientDexLiquidity {
+            pair_id: config.pair_data.pair_id.clone(),
+            available: depth,
+            required: min_liquidity,
+        });
+    }
+    // DEX prices are quoted in base units, convert to whole units like the oracle price
+    let (ntrn_decimals, usd_decimals) = pair_decimals(deps.storage, config)?;
+    from_base_unit_price(price, ntrn_decimals, usd_decimals)
+}
+
+// The non empty ticks that can be bought with token_in, as the token_out reserves
+// and the price in token_in paid per token_out, both in base units
+pub fn query_book(
+    deps: Deps,
+    token_in: &str,
+    token_out: &str,
+) -> ContractResult<Vec<(Uint128, Decimal)>> {
+    let querier = DexQuerier::new(&deps.querier);
+    let tick_liquidity = querier
+        .tick_liquidity_all(
+            get_pair_id_str(token_in, token_out),
+            token_in.to_string(),
+            None,
+        )?
+        .tick_liquidity;
+
+    let mut book = vec![];
+    for liquidity in tick_liquidity.into_iter().filter_map(|t| t.liquidity) {
+        let (reserves, price) = match liquidity {
+            Liquidity::PoolReserves(pool) => (pool.reserves_maker_denom, pool.price_taker_to_maker),
+            Liquidity::LimitOrderTranche(tranche) => {
+                (tranche.reserves_maker_denom, tranche.price_taker_to_maker)
+            }
+        };
+        let reserves = reserves
+            .parse::<Uint128>()
+            .map_err(|_| ContractError::DecodingError)?;
+        if reserves.is_zero() {
+            continue;
+        }
+        book.push((reserves, parse_prec_dec(&price)?));
+    }
+    Ok(book)
+}
+
+// The token_out reserves that can be bought with token_in
+pub fn pool_depth(deps: Deps, token_in: &str, token_out: &str) -> ContractResult<Uint128> {
+    query_book(deps, token_in, token_out)?
+        .into_iter()
+        .try_fold(Uint128::zero(), |depth, (reserves, _)| {
+            Ok(depth.checked_add(reserves)?)
+        })
+}
+
+// The token_in it takes to buy all token_out reserves
+pub fn book_cost(deps: Deps, token_in: &str, token_out: &str) -> ContractResult<Uint128> {
+    query_book(deps, token_in, token_out)?
+        .into_iter()
+        .try_fold(Uint128::zero(), |cost, (reserves, price)| {
+            Ok(cost.checked_add(reserves.mul_floor(price))?)
+        })
+}
+
+// The depth of a venue is the denom_ntrn reserves of the last pool of its route
+pub fn venue_depth(deps: Deps, venue: &Venue) -> ContractResult<Uint128> {
+    match venue.hops.as_slice() {
+        [.., token_in, token_out] => pool_depth(deps, token_in, token_out),
+        _ => Err(ContractError::InvalidRoute {
+            reason: "expected 2-4 denoms".to_string(),
+        }),
+    }
+}
+
+// Splits amount across venues in proportion to their depths. The rounding remainder
+// goes to the deepest venue, and everything to the first venue if none have depth
+pub fn split_by_depth(amount: Uint128, depths: &[Uint128]) -> ContractResult<Vec<Uint128>> {
+    let total_depth = depths
+        .iter()
+        .try_fold(Uint128::zero(), |total, depth| total.checked_add(*depth))?;
+    if depths.is_empty() {
+        return Ok(vec![]);
+    }
+    if total_depth.is_zero() {
+        let mut parts = vec![Uint128::zero(); depths.len()];
+        parts[0] = amount;
+        return Ok(parts);
+    }
+
+    let mut parts = depths
+        .iter()
+        .map(|depth| amount.multiply_ratio(*depth, total_depth))
+        .collect::<Vec<Uint128>>();
+    let allocated = parts
+        .iter()
+        .try_fold(Uint128::zero(), |total, part| total.checked_add(*part))?;
+    let deepest = depths
+        .iter()
+        .enumerate()
+        .max_by(|(i, a), (j, b)| a.cmp(b).then(j.cmp(i)))
+        .map(|(i, _)| i)
+        .unwrap_or_default();
+    parts[deepest] = parts[deepest].checked_add(amount.checked_sub(allocated)?)?;
+    Ok(parts)
+}
+
+// The amount to place given the simulated fill of amount, None to skip the order.
+// Orders expected to fill less than min_fill_ratio are skipped or downsized to the fill
+pub fn checked_fill_amount(
+    expected_fill: Uint128,
+    amount: Uint128,
+    liquidity_check: &LiquidityCheck,
+) -> Option<Uint128> {
+    if expected_fill >= amount.mul_ceil(liquidity_check.min_fill_ratio) {
+        return Some(amount);
+    }
+    if liquidity_check.downsize && !expected_fill.is_zero() {
+        return Some(std::cmp::min(expected_fill, amount));
+    }
+    None
+}
+
+// The denom a schedule is funded with and sells, buys spend usd and sells spend ntrn
+pub fn deposit_denom(config: &Config, side: &ScheduleSide) -> String {
+    match side {
+        ScheduleSide::Buy => config.pair_data.denom_usd.clone(),
+        ScheduleSide::Sell => config.pair_data.denom_ntrn.clone(),
+    }
+}
+
+// The amount a schedule sells if it is run now, zero while it is parked or a maker
+// tranche is live
+pub fn due_amount(schedule: &Schedule, now: u64) -> Uint128 {
+    if schedule.parked
+        || schedule
+            .open_tranches
+            .iter()
+            .any(|tranche| tranche.expiration > now)
+    {
+        return Uint128::zero();
+    }
+    std::cmp::min(schedule.remaining_amount, schedule.max_sell_amount)
+}
+
+// The max amount of denom_usd the runs of a block may sell
+pub fn run_cap_amount(deps: Deps, config: &Config, run_cap: &RunCap) -> ContractResult<Uint128> {
+    match run_cap {
+        RunCap::Absolute { amount } => Ok(*amount),
+        RunCap::DepthFraction { fraction } => Ok(book_cost(
+            deps,
+            &config.pair_data.denom_usd,
+            &config.pair_data.denom_ntrn,
+        )?
+        .mul_floor(*fraction)),
+    }
+}
+
+// Scales amount down by cap / total when the total exceeds the cap, rounding down
+pub fn pro_rata(amount: Uint128, cap: Uint128, total: Uint128) -> Uint128 {
+    if total <= cap {
+        return amount;
+    }
+    amount.multiply_ratio(cap, total)
+}
+
+// The seed of a run's schedule order, from the block height and the schedules nonce
+pub fn run_seed(height: u64, nonce: u128) -> u64 {
+    height ^ (nonce as u64).rotate_left(32) ^ ((nonce >> 64) as u64)
+}
+
+// splitmix64, a small deterministic generator that is good enough to order schedules
+pub fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+// A Fisher-Yates shuffle of the indices 0..len
+pub fn shuffled_order(len: usize, seed: u64) -> Vec<usize> {
+    let mut state = seed;
+    let mut order: Vec<usize> = (0..len).collect();
+    for i in (1..len).rev() {
+        let j = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+        order.swap(i, j);
+    }
+    order
+}
+
+// Splits total in proportion to weights rounding down, returns the shares and
+// what is left over
+pub fn split_pro_rata(
+    total: Uint128,
+    weights: &[Uint128],
+) -> ContractResult<(Vec<Uint128>, Uint128)> {
+    let total_weight = weights
+        .iter()
+        .try_fold(Uint128::zero(), |sum, weight| sum.checked_add(*weight))?;
+    if total_weight.is_zero() {
+        return Ok((vec![Uint128::zero(); weights.len()], total));
+    }
+    let shares: Vec<Uint128> = weights
+        .iter()
+        .map(|weight| total.multiply_ratio(*weight, total_weight))
+        .collect();
+    let allocated = shares
+        .iter()
+        .try_fold(Uint128::zero(), |sum, share| sum.checked_add(*share))?;
+    Ok((shares, total.checked_sub(allocated)?))
+}
+
+// Splits total, at most the sum of weights, in proportion to weights so the shares add
+// up to total exactly. The units lost to rounding go one each to the first shares below
+// their weight
+pub fn split_exact(total: Uint128, weights: &[Uint128]) -> ContractResult<Vec<Uint128>> {
+    let total_weight = weights
+        .iter()
+        .try_fold(Uint128::zero(), |sum, weight| sum.checked_add(*weight))?;
+    if total > total_weight {
+        return Err(ContractError::InsufficientLiquidity {
+            requested: total_weight,
+            available: total,
+        });
+    }
+    let (mut shares, mut remainder) = split_pro_rata(total, weights)?;
+    for (share, weight) in shares.iter_mut().zip(weights) {
+        if remainder.is_zero() {
+            break;
+        }
+        if *share < *weight {
+            *share += Uint128::one();
+            remainder -= Uint128::one();
+        }
+    }
+    Ok(shares)
+}
+
+/// Buy intents selling denom_usd crossed against sell intents selling denom_ntrn.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Netting {
+    // the denom_usd and denom_ntrn that changed hands internally
+    pub matched_usd: Uint128,
+    pub matched_ntrn: Uint128,
+    // the amount taken from each intent, in the denom it sells
+    pub buy_debits: Vec<Uint128>,
+    pub sell_debits: Vec<Uint128>,
+    // the amount paid to each intent, in the denom it buys
+    pub buy_credits: Vec<Uint128>,
+    pub sell_credits: Vec<Uint128>,
+}
+
+// Nets the due amounts of buy and sell intents at price, in denom_usd base units per
+// denom_ntrn base unit. The smaller side is matched completely and the larger side in
+// proportion to its amounts, only the residual has to be traded on the DEX
+pub fn net_intents(buys: &[Uint128], sells: &[Uint128], price: Decimal) -> ContractResult<Netting> {
+    let buy_total = buys
+        .iter()
+        .try_fold(Uint128::zero(), |sum, amount| sum.checked_add(*amount))?;
+    let sell_total = sells
+        .iter()
+        .try_fold(Uint128::zero(), |sum, amount| sum.checked_add(*amount))?;
+    let matched_usd = buy_total.min(sell_total.mul_floor(price));
+    // rounding the denom_ntrn down keeps it within the sell intents
+    let matched_ntrn = if price.is_zero() {
+        Uint128::zero()
+    } else {
+        matched_usd
+            .checked_div_floor(price)
+            .map_err(|_| ContractError::DecimalDivisionError)?
+    };
+    if matched_ntrn.is_zero() {
+        return Ok(Netting {
+            buy_debits: vec![Uint128::zero(); buys.len()],
+            sell_debits: vec![Uint128::zero(); sells.len()],
+            buy_credits: vec![Uint128::zero(); buys.len()],
+            sell_credits: vec![Uint128::zero(); sells.len()],
+            ..Netting::default()
+        });
+    }
+
+    let buy_debits = split_exact(matched_usd, buys)?;
+    let sell_debits = split_exact(matched_ntrn, sells)?;
+    Ok(Netting {
+        matched_usd,
+        matched_ntrn,
+        buy_credits: split_all(matched_ntrn, &buy_debits)?,
+        sell_credits: split_all(matched_usd, &sell_debits)?,
+        buy_debits,
+        sell_debits,
+    })
+}
+
+// Splits all of total in proportion to weights, the units lost to rounding go to the
+// first share with a weight
+pub fn split_all(total: Uint128, weights: &[Uint128]) -> ContractResult<Vec<Uint128>> {
+    let (mut shares, leftover) = split_pro_rata(total, weights)?;
+    if let Some(first) = weights.iter().position(|weight| !weight.is_zero()) {
+        shares[first] = shares[first].checked_add(leftover)?;
+    }
+    Ok(shares)
+}
+
+// The registered decimals of denom_ntrn and denom_usd
+pub fn pair_decimals(storage: &dyn Storage, config: &Config) -> ContractResult<(u32, u32)> {
+    let ntrn = load_enabled_asset(storage, &config.pair_data.denom_ntrn)?;
+    let usd = load_enabled_asset(storage, &config.pair_data.denom_usd)?;
+    Ok((ntrn.decimals, usd.decimals))
+}
+
+// Converts a price of whole quote per whole base into quote base units per base unit,
+// e.g. 0.5 USD per NTRN is 0.5 uusdc per untrn but 500000000000 ausd per untrn
+pub fn to_base_unit_price(
+    price: Decimal,
+    base_decimals: u32,
+    quote_decimals: u32,
+) -> ContractResult<Decimal> {
+    scale_price(price, base_decimals, quote_decimals)
+}
+
+// Converts a price of quote base units per base unit into whole quote per whole base
+pub fn from_base_unit_price(
+    price: Decimal,
+    base_decimals: u32,
+    quote_decimals: u32,
+) -> ContractResult<Decimal> {
+    scale_price(price, quote_decimals, base_decimals)
+}
+
+// Multiplies the price by 10^(to_decimals - from_decimals)
+fn scale_price(price: Decimal, from_decimals: u32, to_decimals: u32) -> ContractResult<Decimal> {
+    let exponent = from_decimals.abs_diff(to_decimals);
+    let factor = 10u128
+        .checked_pow(exponent)
+        .ok_or(ContractError::TooManyDecimals)?;
+    let factor = Decimal::from_atomics(factor, 0).map_err(|_| ContractError::TooManyDecimals)?;
+    let scaled = if to_decimals >= from_decimals {
+        price.checked_mul(factor)?
+    } else {
+        price
+            .checked_div(factor)
+            .map_err(|_| ContractError::DecimalDivisionError)?
+    };
+    // a price scaled below Decimal precision can't be traded at
+    if scaled.is_zero() && !price.is_zero() {
+        return Err(ContractError::InvalidPrice);
+    }
+    Ok(scaled)
+}
+
+// The limit price of a schedule: the price increased by the schedule's slippage
+pub fn apply_slippage(price: Decimal, slippage_basis_points: u128) -> ContractResult<Decimal> {
+    let adjustment = Decimal::from_ratio(
+        10000u128
+            .checked_add(slippage_basis_points)
+            .ok_or(ContractError::ConversionError)?,
+        10000u128,
+    );
+    Ok(price.checked_mul(adjustment)?)
+}
+
+// Errors if the DEX pool price deviates from the oracle price by more than max_bps
+pub fn validate_dex_deviation(
+    deps: Deps,
+    config: &Config,
+    oracle_price: Decimal,
+    max_bps: u64,
+) -> ContractResult<Response> {
+    let min_liquidity = config
+        .dex_fallback
+        .as_ref()
+        .map(|dex_fallback| dex_fallback.min_liquidity)
+        .unwrap_or_default();
+    let dex_price = get_dex_price(deps, config, min_liquidity)?;
+    let deviation_bps = price_deviation_bps(oracle_price, dex_price)?;
+    if deviation_bps > max_bps {
+        return Err(ContractError::PriceDeviationTooHigh {
+            oracle_price,
+            dex_price,
+            deviation_bps,
+            max_bps,
+        });
+    }
+    Ok(Response::new())
+}
+
+// Absolute deviation of price from reference_price in basis points, rounded up
+pub fn price_deviation_bps(reference_price: Decimal, price: Decimal) -> ContractResult<u64> {
+    if reference_price.is_zero() {
+        return Err(ContractError::InvalidPrice);
+    }
+    let difference = reference_price.abs_diff(price);
+    let deviation = difference
+        .checked_mul(Decimal::from_ratio(10000u128, 1u128))?
+        .checked_div(reference_price)
+        .map_err(|_| ContractError::DecimalDivisionError)?;
+    u64::try_from(deviation.to_uint_ceil().u128()).map_err(|_| ContractError::ConversionError)
+}
+
+// Parses a DEX PrecDec string, truncating any precision beyond what Decimal supports
+pub fn parse_prec_dec(value: &str) -> ContractResult<Decimal> {
+    let truncated = match value.split_once('.') {
+        Some((whole, fraction)) if fraction.len() > 18 => format!("{}.{}", whole, &fraction[..18]),
+        _ => value.to_string(),
+    };
+    Decimal::from_str(&truncated).map_err(|_| ContractError::DecimalConversionError)
+}
+
 pub fn normalize_price(price: Int128, decimals: u64) -> ContractResult<Decimal> {
     // Ensure decimals does not exceed u32::MAX
     if decimals > u32::MAX as u64 {
@@ -241,6 +1057,14 @@ pub fn price_to_tick_index(price: Decimal) -> Result<i64, ContractError> {
 }
 
 pub fn extract_amount_in(result: &SubMsgResponse) -> Result<Uint128, ContractError> {
+    decode_response::<MsgPlaceLimitOrderResponse>(result)?
+        .taker_coin_in
+        .and_then(|coin| coin.amount.parse::<Uint128>().ok())
+        .ok_or(ContractError::DecodingError)
+}
+
+// Decodes the first message response of a submessage result
+pub fn decode_response<T: Message + Default>(result: &SubMsgResponse) -> ContractResult<T> {
     let response_data = result
         .msg_responses
         .get(0)
@@ -248,22 +1072,307 @@ pub fn extract_amount_in(result: &SubMsgResponse) -> Result<Uint128, ContractErr
         .value
         .clone();
 
-    MsgPlaceLimitOrderResponse::decode(response_data.as_slice())
-        .map_err(|_| ContractError::DecodingError)?
-        .taker_coin_in
-        .and_then(|coin| coin.amount.parse::<Uint128>().ok())
-        .ok_or(ContractError::DecodingError)
+    T::decode(response_data.as_slice()).map_err(|_| ContractError::DecodingError)
+}
+
+pub fn coin_amount(coin: Option<Coin>) -> Uint128 {
+    coin.map(|coin| coin.amount).unwrap_or_default()
+}
+
+// Converts an optional proto coin into a cosmwasm coin, None if absent or empty
+pub fn to_coin(coin: Option<ProtoCoin>) -> ContractResult<Option<Coin>> {
+    match coin {
+        Some(coin) => {
+            let amount = coin
+                .amount
+                .parse::<Uint128>()
+                .map_err(|_| ContractError::DecodingError)?;
+            if amount.is_zero() {
+                return Ok(None);
+            }
+            Ok(Some(Coin {
+                denom: coin.denom,
+                amount,
+            }))
+        }
+        None => Ok(None),
+    }
+}
+
+// max number of denoms in a multi-hop route, including the entry and exit denom
+pub const MAX_ROUTE_LENGTH: usize = 4;
+
+// The route used to buy target_denom: the owner configured route if set,
+// otherwise the shortest route over the known DEX pairs
+pub fn find_route(
+    storage: &dyn Storage,
+    config: &Config,
+    target_denom: &str,
+) -> ContractResult<Vec<String>> {
+    if let Some(route) = ROUTES.may_load(storage, target_denom)? {
+        return Ok(route);
+    }
+    discover_route(storage, &config.pair_data.denom_usd, target_denom)
+}
+
+// Breadth first search over DEX_PAIRS for the shortest route from one denom to another
+pub fn discover_route(storage: &dyn Storage, from: &str, to: &str) -> ContractResult<Vec<String>> {
+    let pairs = DEX_PAIRS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, pair)| pair))
+        .collect::<StdResult<Vec<(String, String)>>>()?;
+
+    let mut queue: VecDeque<Vec<String>> = VecDeque::from([vec![from.to_string()]]);
+    while let Some(route) = queue.pop_front() {
+        let last = route.last().cloned().unwrap_or_default();
+        if last == to {
+            return Ok(route);
+        }
+        if route.len() >= MAX_ROUTE_LENGTH {
+            continue;
+        }
+        for (denom_a, denom_b) in pairs.iter() {
+            let next = if *denom_a == last {
+                denom_b
+            } else if *denom_b == last {
+                denom_a
+            } else {
+                continue;
+            };
+            if !route.contains(next) {
+                let mut extended = route.clone();
+                extended.push(next.clone());
+                queue.push_back(extended);
+            }
+        }
+    }
+    Err(ContractError::RouteNotFound {
+        from: from.to_string(),
+        to: to.to_string(),
+    })
+}
+
+// Checks a route starts at from, ends at to and never revisits a denom
+pub fn validate_route(hops: &[String], from: &str, to: &str) -> ContractResult<Response> {
+    let invalid_route = |reason: &str| ContractError::InvalidRoute {
+        reason: reason.to_string(),
+    };
+    if hops.len() < 2 || hops.len() > MAX_ROUTE_LENGTH {
+        return Err(invalid_route("expected 2-4 denoms"));
+    }
+    if hops.first().map(String::as_str) != Some(from) {
+        return Err(invalid_route("must start at denom_usd"));
+    }
+    if hops.last().map(String::as_str) != Some(to) {
+        return Err(invalid_route("must end at the target denom"));
+    }
+    for (i, hop) in hops.iter().enumerate() {
+        if hops[..i].contains(hop) {
+            return Err(invalid_route("denoms cannot repeat"));
+        }
+    }
+    Ok(Response::new())
+}
+
+// The min amount of target base units per denom_usd base unit a swap may exit at
+pub fn exit_limit_price(
+    target_price: Decimal,
+    slippage_basis_points: u128,
+    usd_decimals: u32,
+    target_decimals: u32,
+) -> ContractResult<Decimal> {
+    let max_price = apply_slippage(target_price, slippage_basis_points)?;
+    // invert USD per target into target per USD
+    let min_out_per_in = cross_price(Decimal::one(), max_price)?;
+    to_base_unit_price(min_out_per_in, usd_decimals, target_decimals)
+}
+
+// Validates the combination of order type and price protections of a schedule
+pub fn validate_order_config(
+    order_type: &ScheduleOrderType,
+    max_slippage_basis_points: u128,
+    max_average_slippage_basis_points: Option<u128>,
+    slippage_ladder: &[u128],
+    tranche_ladder: &[TrancheStep],
+) -> ContractResult<Response> {
+    if let ScheduleOrderType::GoodTilTime { expiry_seconds } = order_type {
+        if *expiry_seconds == 0 {
+            return Err(ContractError::MalformedInput {
+                input: "expiry_seconds".to_string(),
+                reason: "must be >=1".to_string(),
+            });
+        }
+    }
+    if let Some(max_average_slippage_basis_points) = max_average_slippage_basis_points {
+        // maker orders rest at the oracle price, only taker fills have an average price
+        if order_type.is_maker() {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "average price protection is only supported for taker orders".to_string(),
+            });
+        }
+        if max_average_slippage_basis_points > max_slippage_basis_points {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "average slippage cannot exceed max slippage".to_string(),
+            });
+        }
+    }
+    if !slippage_ladder.is_empty() {
+        // only taker orders that can fill nothing are retried
+        if !matches!(order_type, ScheduleOrderType::ImmediateOrCancel) {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "slippage ladders are only supported for ImmediateOrCancel".to_string(),
+            });
+        }
+        if slippage_ladder.windows(2).any(|steps| steps[0] >= steps[1])
+            || slippage_ladder
+                .last()
+                .is_some_and(|step| *step > max_slippage_basis_points)
+        {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "slippage ladder steps must increase up to max slippage".to_string(),
+            });
+        }
+    }
+    if !tranche_ladder.is_empty() {
+        if !matches!(order_type, ScheduleOrderType::ImmediateOrCancel) {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "tranche ladders are only supported for ImmediateOrCancel".to_string(),
+            });
+        }
+        // a retried order would be placed again at the slippage ladder's prices
+        if !slippage_ladder.is_empty() {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "a schedule cannot have both a slippage and a tranche ladder".to_string(),
+            });
+        }
+        if tranche_ladder
+            .windows(2)
+            .any(|steps| steps[0].slippage_basis_points >= steps[1].slippage_basis_points)
+            || tranche_ladder
+                .last()
+                .is_some_and(|step| step.slippage_basis_points > max_slippage_basis_points)
+        {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "tranche ladder prices must increase up to max slippage".to_string(),
+            });
+        }
+        let total = tranche_ladder
+            .iter()
+            .try_fold(Decimal::zero(), |total, step| {
+                total.checked_add(step.fraction)
+            })?;
+        if tranche_ladder.iter().any(|step| step.fraction.is_zero()) || total != Decimal::one() {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "tranche ladder fractions must be >0 and add up to 1".to_string(),
+            });
+        }
+    }
+    Ok(Response::new())
+}
+
+/// Why a schedule's order did not fill, reported in events and counted towards parking.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderFailure {
+    // the order was placed but nothing filled
+    NoFill,
+    // the book had no liquidity within the order's limit or average price
+    PriceLimit,
+    // the venue had no liquidity to trade against
+    NoLiquidity,
+    // the contract could not fund the order
+    InsufficientFunds,
+    // the reply of the order could not be decoded
+    InvalidReply,
+    Other,
+}
+
+impl OrderFailure {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderFailure::NoFill => "no_fill",
+            OrderFailure::PriceLimit => "price_limit",
+            OrderFailure::NoLiquidity => "no_liquidity",
+            OrderFailure::InsufficientFunds => "insufficient_funds",
+            OrderFailure::InvalidReply => "invalid_reply",
+            OrderFailure::Other => "other",
+        }
+    }
+}
+
+// Classifies the error of a failed order from the messages of the DEX, Astroport and
+// the bank module
+pub fn classify_order_error(err: &str) -> OrderFailure {
+    let err = err.to_lowercase();
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|pattern| err.contains(pattern));
+    if contains_any(&["insufficient funds"]) {
+        OrderFailure::InsufficientFunds
+    } else if contains_any(&[
+        "limitprice",
+        "limit price",
+        "averagesellprice",
+        "average sell price",
+        "max spread",
+    ]) {
+        OrderFailure::PriceLimit
+    } else if contains_any(&["liquidity", "fill or kill", "rounded output of 0"]) {
+        OrderFailure::NoLiquidity
+    } else {
+        OrderFailure::Other
+    }
+}
+
+// Closes the schedules left with less than the min tradable amount of the denom they sell,
+// which the DEX cannot fill, and refunds the remainder to their owners. Dust is closed at
+// the start of a run and once the last part of a schedule's run has replied, replies only
+// close the schedules they settled. Schedules with open tranches are kept until the
+// tranches are withdrawn
+pub fn close_dust_schedules(
+    config: &Config,
+    schedules: &mut Schedules,
+    schedule_ids: Option<&[u128]>,
+) -> Vec<CosmosMsg> {
+    let mut refunds: Vec<CosmosMsg> = vec![];
+    schedules.schedules.retain(|schedule| {
+        let denom = deposit_denom(config, &schedule.side);
+        let is_dust = schedule_ids.map_or(true, |ids| ids.contains(&schedule.id))
+            && !schedule.remaining_amount.is_zero()
+            && schedule.remaining_amount < min_tradable_amount(config, &denom)
+            && schedule.open_tranches.is_empty();
+        if is_dust {
+            refunds.push(
+                BankMsg::Send {
+                    to_address: schedule.owner.to_string(),
+                    amount: vec![Coin {
+                        denom,
+                        amount: schedule.remaining_amount,
+                    }],
+                }
+                .into(),
+            );
+        }
+        !is_dust
+    });
+    refunds
+}
+
+// The min tradable amount of a denom, zero if it has none
+pub fn min_tradable_amount(config: &Config, denom: &str) -> Uint128 {
+    config
+        .min_tradable_amounts
+        .iter()
+        .find(|coin| coin.denom == denom)
+        .map_or(Uint128::zero(), |coin| coin.amount)
 }
 
 pub fn update_schedules(
     schedules: &mut Schedules,
-    schedule_id: u64,
+    schedule_id: u128,
     amount_in: Uint128,
 ) -> Result<(), ContractError> {
     let schedule = schedules
         .schedules
         .iter_mut()
-        .find(|s| s.id == schedule_id as u128)
+        .find(|s| s.id == schedule_id)
         .ok_or(ContractError::ScheduleNotFound)?;
 
     if amount_in > schedule.remaining_amount {
@@ -275,8 +1384,9 @@ pub fn update_schedules(
 
     schedule.remaining_amount -= amount_in;
 
-    if schedule.remaining_amount.is_zero() {
-        schedules.schedules.retain(|s| s.id != schedule_id as u128);
+    // schedules with resting maker tranches are kept until the tranches are withdrawn
+    if schedule.remaining_amount.is_zero() && schedule.open_tranches.is_empty() {
+        schedules.schedules.retain(|s| s.id != schedule_id);
     }
 
     Ok(())
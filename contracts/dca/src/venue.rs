@@ -0,0 +1,299 @@
+use crate::error::{ContractError, ContractResult};
+use crate::execute::to_dex_price;
+use crate::msg::{
+    AstroportAsset, AstroportAssetInfo, AstroportExecuteMsg, AstroportQueryMsg,
+    AstroportSimulationResponse,
+};
+use crate::state::{ExecutionVenueConfig, ScheduleOrderType};
+use crate::utils::{coin_amount, cross_price, decode_response, to_base_unit_price, to_coin};
+use cosmwasm_std::{
+    to_json_binary, Coin, CosmosMsg, Decimal, Deps, Env, SubMsgResponse, Uint128, WasmMsg,
+};
+use neutron_std::shim::Timestamp;
+use neutron_std::types::neutron::dex::{
+    DexQuerier, LimitOrderType, MsgPlaceLimitOrder, MsgPlaceLimitOrderResponse,
+};
+
+/// A swap of token_in for token_out requested from a venue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwapRequest {
+    // receives token_out of taker orders, maker orders are received by the contract
+    pub receiver: String,
+    pub token_in: String,
+    pub token_out: String,
+    pub amount_in: Uint128,
+    // the max price in whole token_in per whole token_out
+    pub limit_price: Decimal,
+    // the max average price of the whole fill in whole units, if bounded
+    pub max_average_price: Option<Decimal>,
+    pub order_type: ScheduleOrderType,
+    pub decimals_in: u32,
+    pub decimals_out: u32,
+}
+
+/// The amounts of a swap decoded from its reply.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct SwapResult {
+    // the amount of token_in that was swapped
+    pub amount_in: Uint128,
+    // the amount of token_out sent to the receiver
+    pub amount_out: Uint128,
+    // the amount of token_in that left the contract, more than amount_in if the
+    // rest of the order rests on the book
+    pub placed: Uint128,
+    // the maker tranche the resting part of the order was placed in
+    pub tranche_key: Option<String>,
+}
+
+/// An exchange the orders of the pair are executed on.
+pub trait ExecutionVenue {
+    // the name the venue is reported under in attributes
+    fn name(&self) -> String;
+    // whether orders can rest on the venue's book as maker tranches
+    fn supports_maker_orders(&self) -> bool;
+    // the message executing the swap from the contract
+    fn swap_msg(&self, env: &Env, request: &SwapRequest) -> ContractResult<CosmosMsg>;
+    // the amounts swapped by a successful swap message
+    fn decode_reply(&self, result: &SubMsgResponse) -> ContractResult<SwapResult>;
+    // the amount of token_in the swap is expected to fill if executed now
+    fn simulate_fill(
+        &self,
+        deps: Deps,
+        env: &Env,
+        request: &SwapRequest,
+    ) -> ContractResult<Uint128>;
+}
+
+/// Limit orders on the Neutron DEX.
+pub struct NeutronDex;
+
+impl ExecutionVenue for NeutronDex {
+    fn name(&self) -> String {
+        "neutron_dex".to_string()
+    }
+
+    fn supports_maker_orders(&self) -> bool {
+        true
+    }
+
+    fn swap_msg(&self, env: &Env, request: &SwapRequest) -> ContractResult<CosmosMsg> {
+        Ok(self.place_limit_order(env, request)?.into())
+    }
+
+    fn decode_reply(&self, result: &SubMsgResponse) -> ContractResult<SwapResult> {
+        let response = decode_response::<MsgPlaceLimitOrderResponse>(result)?;
+        Ok(SwapResult {
+            amount_in: coin_amount(to_coin(response.taker_coin_in)?),
+            amount_out: coin_amount(to_coin(response.taker_coin_out)?),
+            placed: coin_amount(to_coin(response.coin_in)?),
+            tranche_key: Some(response.tranche_key).filter(|key| !key.is_empty()),
+        })
+    }
+
+    fn simulate_fill(
+        &self,
+        deps: Deps,
+        env: &Env,
+        request: &SwapRequest,
+    ) -> ContractResult<Uint128> {
+        let response = DexQuerier::new(&deps.querier)
+            .simulate_place_limit_order(Some(self.place_limit_order(env, request)?))?
+            .resp
+            .ok_or(ContractError::NoResponseData)?;
+        Ok(coin_amount(to_coin(response.taker_coin_in)?))
+    }
+}
+
+impl NeutronDex {
+    fn place_limit_order(
+        &self,
+        env: &Env,
+        request: &SwapRequest,
+    ) -> ContractResult<MsgPlaceLimitOrder> {
+        let now = env.block.time.seconds();
+        let (order_type, expiration_time) = match request.order_type {
+            // fills as much as it can at the limit price and cancels the rest
+            ScheduleOrderType::ImmediateOrCancel => (LimitOrderType::ImmediateOrCancel, None),
+            // either the whole amount fills at the limit price or the order fails
+            ScheduleOrderType::FillOrKill => (LimitOrderType::FillOrKill, None),
+            // whatever does not fill immediately rests on the book until it expires
+            ScheduleOrderType::GoodTilTime { expiry_seconds } => (
+                LimitOrderType::GoodTilTime,
+                Some(Timestamp {
+                    seconds: now.saturating_add(expiry_seconds) as i64,
+                    nanos: 0,
+                }),
+            ),
+            // the resting tranche is only fillable in this block
+            ScheduleOrderType::JustInTime => (LimitOrderType::JustInTime, None),
+        };
+
+        // both bounds are sell prices of token_in, the min token_out per token_in
+        let min_average_sell_price = request
+            .max_average_price
+            .map(|max_average_price| dex_sell_price(max_average_price, request))
+            .transpose()?;
+        let limit_sell_price = dex_sell_price(request.limit_price, request)?;
+
+        // a maker tranche is owned by the receiver of the order, the contract must own it
+        // to withdraw and cancel it and forwards the proceeds to the schedule owner
+        let receiver = match request.order_type.is_maker() {
+            true => env.contract.address.to_string(),
+            false => request.receiver.clone(),
+        };
+
+        Ok(MsgPlaceLimitOrder {
+            creator: env.contract.address.to_string(),
+            receiver,
+            token_in: request.token_in.clone(),
+            token_out: request.token_out.clone(),
+            tick_index_in_to_out: 0,
+            amount_in: request.amount_in.to_string(),
+            order_type: order_type.into(),
+            expiration_time,
+            min_average_sell_price,
+            max_amount_out: None,
+            limit_sell_price: Some(limit_sell_price),
+        })
+    }
+}
+
+// The DEX bounds orders from below by the price token_in is sold at, in token_out base units
+// per token_in base unit. Request prices are the max price paid in whole token_in per whole
+// token_out, so a higher max price is a lower sell price
+fn dex_sell_price(price: Decimal, request: &SwapRequest) -> ContractResult<String> {
+    to_dex_price(to_base_unit_price(
+        cross_price(Decimal::one(), price)?,
+        request.decimals_in,
+        request.decimals_out,
+    )?)
+}
+
+/// Swaps against an Astroport pair contract. Swaps either fill completely within
+/// the price bound or fail, nothing rests on the book.
+pub struct Astroport {
+    pub pair_address: String,
+}
+
+impl ExecutionVenue for Astroport {
+    fn name(&self) -> String {
+        format!("astroport:{}", self.pair_address)
+    }
+
+    fn supports_maker_orders(&self) -> bool {
+        false
+    }
+
+    fn swap_msg(&self, _env: &Env, request: &SwapRequest) -> ContractResult<CosmosMsg> {
+        if request.order_type.is_maker() {
+            return Err(ContractError::InvalidOrderConfig {
+                reason: "astroport only supports taker orders".to_string(),
+            });
+        }
+
+        Ok(WasmMsg::Execute {
+            contract_addr: self.pair_address.clone(),
+            msg: to_json_binary(&AstroportExecuteMsg::Swap {
+                offer_asset: offer_asset(request),
+                ask_asset_info: Some(AstroportAssetInfo::NativeToken {
+                    denom: request.token_out.clone(),
+                }),
+                belief_price: Some(belief_price(request)?),
+                max_spread: Some(Decimal::zero()),
+                to: Some(request.receiver.clone()),
+            })?,
+            funds: vec![Coin {
+                denom: request.token_in.clone(),
+                amount: request.amount_in,
+            }],
+        }
+        .into())
+    }
+
+    fn decode_reply(&self, result: &SubMsgResponse) -> ContractResult<SwapResult> {
+        // the pair reports the swap in the attributes of its wasm event
+        let event = result
+            .events
+            .iter()
+            .find(|event| {
+                event.ty == "wasm"
+                    && event.attributes.iter().any(|attr| {
+                        attr.key == "_contract_address" && attr.value == self.pair_address
+                    })
+                    && event
+                        .attributes
+                        .iter()
+                        .any(|attr| attr.key == "action" && attr.value == "swap")
+            })
+            .ok_or(ContractError::NoResponseData)?;
+        let amount = |key: &str| -> ContractResult<Uint128> {
+            event
+                .attributes
+                .iter()
+                .find(|attr| attr.key == key)
+                .ok_or(ContractError::DecodingError)?
+                .value
+                .parse::<Uint128>()
+                .map_err(|_| ContractError::DecodingError)
+        };
+        let amount_in = amount("offer_amount")?;
+
+        Ok(SwapResult {
+            amount_in,
+            amount_out: amount("return_amount")?,
+            placed: amount_in,
+            tranche_key: None,
+        })
+    }
+
+    fn simulate_fill(
+        &self,
+        deps: Deps,
+        _env: &Env,
+        request: &SwapRequest,
+    ) -> ContractResult<Uint128> {
+        let simulation: AstroportSimulationResponse = deps.querier.query_wasm_smart(
+            self.pair_address.clone(),
+            &AstroportQueryMsg::Simulation {
+                offer_asset: offer_asset(request),
+                ask_asset_info: Some(AstroportAssetInfo::NativeToken {
+                    denom: request.token_out.clone(),
+                }),
+            },
+        )?;
+        // the swap fills completely if it returns at least offer / belief_price, else not at all
+        if simulation.return_amount.mul_floor(belief_price(request)?) >= request.amount_in {
+            return Ok(request.amount_in);
+        }
+        Ok(Uint128::zero())
+    }
+}
+
+fn offer_asset(request: &SwapRequest) -> AstroportAsset {
+    AstroportAsset {
+        info: AstroportAssetInfo::NativeToken {
+            denom: request.token_in.clone(),
+        },
+        amount: request.amount_in,
+    }
+}
+
+// The swap is all or nothing so its average price is its only price. Astroport
+// rejects swaps returning less than offer / belief_price when max_spread is zero
+fn belief_price(request: &SwapRequest) -> ContractResult<Decimal> {
+    let price = request
+        .max_average_price
+        .map_or(request.limit_price, |max_average_price| {
+            max_average_price.min(request.limit_price)
+        });
+    to_base_unit_price(price, request.decimals_out, request.decimals_in)
+}
+
+pub fn build_execution_venue(venue: &ExecutionVenueConfig) -> Box<dyn ExecutionVenue> {
+    match venue {
+        ExecutionVenueConfig::NeutronDex {} => Box::new(NeutronDex),
+        ExecutionVenueConfig::Astroport { pair_address } => Box::new(Astroport {
+            pair_address: pair_address.clone(),
+        }),
+    }
+}
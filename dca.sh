@@ -17,7 +17,7 @@ sleep 1
 
 code_id=$(neutrond q tx $tx_hash --output json --node $node| jq -r '.events.[] | select(.type == "store_code") | .attributes.[] | select(.key == "code_id") | .value')
 echo "code_id: $code_id"
-resp=$(neutrond tx wasm instantiate $code_id '{"owner": "neutron13nfu3ct5xkr0vlswgk3gl9zazp7zan88edz67j", "denom_ntrn": "untrn", "denom_usd": "uibcusdc", "max_block_old": 20, "max_schedules": 20}' --label test-mmvault --admin neutron13nfu3ct5xkr0vlswgk3gl9zazp7zan88edz67j --gas auto --output json --chain-id $chain_id --from $account --gas-prices 0.125untrn --gas-adjustment 1.5 -y)
+resp=$(neutrond tx wasm instantiate $code_id '{"owner": "neutron13nfu3ct5xkr0vlswgk3gl9zazp7zan88edz67j", "denom_ntrn": "untrn", "denom_usd": "uibcusdc", "assets": [{"denom": "untrn", "ticker": "NTRN", "decimals": 6, "enabled": true}, {"denom": "uibcusdc", "ticker": "USD", "decimals": 6, "enabled": true}], "oracle_policy": {"max_blocks_old": 20, "max_seconds_old": 60, "require_enabled": true, "min_provider_count": 1}, "max_schedules": 20}' --label test-mmvault --admin neutron13nfu3ct5xkr0vlswgk3gl9zazp7zan88edz67j --gas auto --output json --chain-id $chain_id --from $account --gas-prices 0.125untrn --gas-adjustment 1.5 -y)
 tx_hash=$(echo $resp | jq -r ".txhash")
 sleep 1
 